@@ -0,0 +1,133 @@
+//! `#[derive(QuandlRow)]` for the `quandl` crate. Use it through the `derive` feature of
+//! `quandl`, which re-exports the macro next to the `QuandlRow` trait.
+
+#![deny(missing_docs, missing_debug_implementations, unused_qualifications)]
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Field, Fields, Lit, Meta, NestedMeta};
+
+/// Implements `quandl::QuandlRow` for a struct with named fields.
+///
+/// - `#[quandl(date)]` marks the field receiving the date of the row.
+/// - `#[quandl(column = "Adj. Close")]` reads the field from the given column instead of the
+///   column with the name of the field.
+/// - `#[quandl(optional)]` allows the column to be missing from the response, in which case the
+///   field, usually an `Option`, is decoded from a missing value.
+#[proc_macro_derive(QuandlRow, attributes(quandl))]
+pub fn derive_quandl_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// A field of the struct and its `#[quandl(...)]` attributes.
+struct RowField {
+    ident: syn::Ident,
+    column: String,
+    date: bool,
+    optional: bool,
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match input.data {
+        Data::Struct(ref data) => {
+            match data.fields {
+                Fields::Named(ref fields) => &fields.named,
+                _ => {
+                    return Err(syn::Error::new_spanned(&input.ident,
+                                                       "QuandlRow requires named fields"))
+                }
+            }
+        }
+        _ => return Err(syn::Error::new_spanned(&input.ident, "QuandlRow requires a struct")),
+    };
+
+    let mut fields = fields.iter().map(parse_field).collect::<syn::Result<Vec<_>>>()?;
+    if fields.iter().filter(|field| field.date).count() > 1 {
+        return Err(syn::Error::new_spanned(&input.ident,
+                                           "only one field can be marked #[quandl(date)]"));
+    }
+
+    let mut columns = Vec::new();
+    let mut inits = Vec::new();
+    for field in fields.drain(..) {
+        let ident = field.ident;
+        if field.date {
+            inits.push(quote!(#ident: row.date));
+        } else {
+            let index = columns.len();
+            let column = field.column;
+            let optional = field.optional;
+            columns.push(quote!((#column, #optional)));
+            inits.push(quote!(#ident: layout.get(#index, row)?));
+        }
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::quandl::QuandlRow for #name #ty_generics #where_clause {
+            fn layout(column_names: &[::std::string::String])
+                      -> ::quandl::Result<::quandl::RowLayout> {
+                ::quandl::RowLayout::new(column_names, &[#(#columns),*])
+            }
+
+            fn decode(layout: &::quandl::RowLayout, row: &::quandl::Row)
+                      -> ::quandl::Result<Self> {
+                ::std::result::Result::Ok(#name { #(#inits),* })
+            }
+        }
+    })
+}
+
+fn parse_field(field: &Field) -> syn::Result<RowField> {
+    let ident = match field.ident {
+        Some(ref ident) => ident.clone(),
+        None => return Err(syn::Error::new_spanned(field, "QuandlRow requires named fields")),
+    };
+    let mut row_field = RowField {
+        column: ident.to_string(),
+        ident: ident,
+        date: false,
+        optional: false,
+    };
+
+    for attr in field.attrs.iter().filter(|attr| attr.path.is_ident("quandl")) {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(syn::Error::new_spanned(meta, "expected #[quandl(...)]")),
+        };
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(ref pair)) if pair.path.is_ident("column") => {
+                    match pair.lit {
+                        Lit::Str(ref column) => row_field.column = column.value(),
+                        ref lit => {
+                            return Err(syn::Error::new_spanned(lit,
+                                                               "expected a column name string"))
+                        }
+                    }
+                }
+                NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("date") => {
+                    row_field.date = true
+                }
+                NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident("optional") => {
+                    row_field.optional = true
+                }
+                nested => {
+                    return Err(syn::Error::new_spanned(nested,
+                                                       "unknown quandl attribute, expected \
+                                                        `column = \"...\"`, `date` or \
+                                                        `optional`"))
+                }
+            }
+        }
+    }
+    Ok(row_field)
+}
@@ -0,0 +1,187 @@
+use super::{Bar, NaiveDate};
+
+/// Compute the split and dividend adjusted fields of `bars` from their raw prices, `ex_dividend`
+/// and `split_ratio`, using the CRSP methodology of the `Adj.` columns of `WIKI`. Missing
+/// dividends and split ratios are treated as no event. The adjusted fields of the input are
+/// replaced; all other fields are kept. Bars may be in ascending or descending order; the result
+/// is in the same order.
+///
+/// Adjusted values of the latest bar equal its raw values. Going back in time, at each ex-date
+/// `t` all earlier prices are multiplied by `(1 / s) * (1 - d * s / c)` and all earlier volumes
+/// by `s`, where `d` is the dividend of `t`, `s` its split ratio and `c` the raw close of the bar
+/// before `t`. The dividend is quoted after the split, hence the `d * s` term when both happen
+/// on the same day.
+pub fn adjust(bars: &[Bar]) -> Vec<Bar> {
+    let factors = factors(bars);
+    bars.iter()
+        .zip(factors.iter())
+        .map(|(bar, &(price, volume))| {
+            Bar {
+                adj_open: Some(bar.open * price),
+                adj_high: Some(bar.high * price),
+                adj_low: Some(bar.low * price),
+                adj_close: Some(bar.close * price),
+                adj_volume: Some(bar.volume * volume),
+                ..bar.clone()
+            }
+        })
+        .collect()
+}
+
+/// Cumulative price and volume adjustment factors of each bar, in the order of `bars`. See
+/// `adjust`.
+pub fn factors(bars: &[Bar]) -> Vec<(f64, f64)> {
+    let mut chronological: Vec<usize> = (0..bars.len()).collect();
+    chronological.sort_by(|&a, &b| bars[a].date.cmp(&bars[b].date));
+
+    let mut factors = vec![(1.0, 1.0); bars.len()];
+    let (mut price, mut volume) = (1.0, 1.0);
+    for (pos, &i) in chronological.iter().enumerate().rev() {
+        factors[i] = (price, volume);
+        if pos == 0 {
+            break;
+        }
+        let bar = &bars[i];
+        let previous_close = bars[chronological[pos - 1]].close;
+        let split = match bar.split_ratio {
+            Some(split) if split > 0.0 => split,
+            _ => 1.0,
+        };
+        let dividend = match bar.ex_dividend {
+            Some(dividend) if previous_close > 0.0 && dividend * split < previous_close => {
+                1.0 - dividend * split / previous_close
+            }
+            _ => 1.0,
+        };
+        price *= dividend / split;
+        volume *= split;
+    }
+    factors
+}
+
+/// An adjusted value of a bar that differs from the value computed by `adjust`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Discrepancy {
+    /// Date of the bar
+    pub date: NaiveDate,
+    /// Name of the field, e.g. `adj_close`
+    pub field: &'static str,
+    /// Value of the bar
+    pub actual: f64,
+    /// Value computed by `adjust`
+    pub expected: f64,
+}
+
+/// Compare the adjusted fields of `bars`, e.g. from `Quandl::bars("WIKI/AAPL")`, with the
+/// values computed by `adjust`. Values differing by more than `tolerance` relative to the
+/// computed value are reported; missing adjusted values are skipped.
+pub fn check(bars: &[Bar], tolerance: f64) -> Vec<Discrepancy> {
+    let mut discrepancies = Vec::new();
+    for (bar, adjusted) in bars.iter().zip(adjust(bars).iter()) {
+        let fields = [("adj_open", bar.adj_open, adjusted.adj_open),
+                      ("adj_high", bar.adj_high, adjusted.adj_high),
+                      ("adj_low", bar.adj_low, adjusted.adj_low),
+                      ("adj_close", bar.adj_close, adjusted.adj_close),
+                      ("adj_volume", bar.adj_volume, adjusted.adj_volume)];
+        for &(field, actual, expected) in &fields {
+            if let (Some(actual), Some(expected)) = (actual, expected) {
+                if (actual - expected).abs() > tolerance * expected.abs() {
+                    discrepancies.push(Discrepancy {
+                        date: bar.date,
+                        field: field,
+                        actual: actual,
+                        expected: expected,
+                    });
+                }
+            }
+        }
+    }
+    discrepancies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{Bar, NaiveDate};
+
+    fn bar(day: u32, close: f64, ex_dividend: f64, split_ratio: f64) -> Bar {
+        Bar {
+            date: NaiveDate::from_ymd(2015, 1, day),
+            open: close,
+            high: close + 1.0,
+            low: close - 1.0,
+            close: close,
+            volume: 1000.0,
+            ex_dividend: Some(ex_dividend),
+            split_ratio: Some(split_ratio),
+            adj_open: None,
+            adj_high: None,
+            adj_low: None,
+            adj_close: None,
+            adj_volume: None,
+        }
+    }
+
+    fn bars() -> Vec<Bar> {
+        // a dividend of 2 on the 6th and a 2-for-1 split on the 7th, in Quandl's order
+        vec![bar(8, 52.0, 0.0, 1.0),
+             bar(7, 51.0, 0.0, 2.0),
+             bar(6, 102.0, 2.0, 1.0),
+             bar(5, 100.0, 0.0, 1.0)]
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn test_factors() {
+        let factors = factors(&bars());
+        let expected = [(1.0, 1.0), (1.0, 1.0), (0.5, 2.0), (0.49, 2.0)];
+        for (actual, expected) in factors.iter().zip(expected.iter()) {
+            assert_close(actual.0, expected.0);
+            assert_close(actual.1, expected.1);
+        }
+
+        // the order of the input doesn't matter
+        let mut asc = bars();
+        asc.reverse();
+        let mut asc_factors = super::factors(&asc);
+        asc_factors.reverse();
+        assert_eq!(asc_factors, factors);
+        assert!(super::factors(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_adjust() {
+        let adjusted = adjust(&bars());
+        assert_eq!(adjusted[0].adj_close, Some(52.0));
+        assert_close(adjusted[2].adj_close.unwrap(), 51.0);
+        assert_close(adjusted[2].adj_high.unwrap(), 51.5);
+        assert_close(adjusted[3].adj_close.unwrap(), 49.0);
+        assert_close(adjusted[3].adj_volume.unwrap(), 2000.0);
+        assert_eq!(adjusted[3].close, 100.0);
+    }
+
+    #[test]
+    fn test_same_day_split_and_dividend() {
+        // a 2-for-1 split with a post-split dividend of 1 on the 6th
+        let bars = vec![bar(6, 50.0, 1.0, 2.0), bar(5, 100.0, 0.0, 1.0)];
+        assert_close(factors(&bars)[1].0, 0.5 * 0.98);
+    }
+
+    #[test]
+    fn test_check() {
+        let bars = adjust(&bars());
+        assert!(check(&bars, 1e-9).is_empty());
+
+        let mut wrong = bars.clone();
+        wrong[3].adj_close = Some(50.0);
+        wrong[0].adj_open = None;
+        let discrepancies = check(&wrong, 1e-6);
+        assert_eq!(discrepancies.len(), 1);
+        assert_eq!(discrepancies[0].date, NaiveDate::from_ymd(2015, 1, 5));
+        assert_eq!(discrepancies[0].field, "adj_close");
+        assert_close(discrepancies[0].expected, 49.0);
+    }
+}
@@ -0,0 +1,211 @@
+use error::Result;
+use typed::{QuandlRow, RowLayout};
+use super::{NaiveDate, Row, Dataset};
+
+/// Column names used for each field of a `Bar` by the equity price databases, in order of
+/// preference. `WIKI` uses e.g. `Ex-Dividend` and `Adj. Close`, `EOD` uses `Dividend` and
+/// `Adj_Close`, and some exchange databases use `Last` and `Total Trade Quantity`.
+const COLUMNS: &'static [(&'static [&'static str], bool)] =
+    &[(&["Open"], false),
+      (&["High"], false),
+      (&["Low"], false),
+      (&["Close", "Last", "Settle"], false),
+      (&["Volume", "Total Trade Quantity", "Shares Traded"], false),
+      (&["Ex-Dividend", "Dividend"], true),
+      (&["Split Ratio", "Split"], true),
+      (&["Adj. Open", "Adj_Open"], true),
+      (&["Adj. High", "Adj_High"], true),
+      (&["Adj. Low", "Adj_Low"], true),
+      (&["Adj. Close", "Adj_Close"], true),
+      (&["Adj. Volume", "Adj_Volume"], true)];
+
+/// A daily price bar of an equity price database like `WIKI` or `EOD`, fetched with
+/// `Quandl::bars`. The dividend, split and adjusted fields are `None` for databases without
+/// those columns, and for days without a value. The price and volume fields are always set:
+/// `decode_bars` skips rows where one of them is missing, while `QuandlRow::decode` fails.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bar {
+    /// Date of the bar
+    pub date: NaiveDate,
+    /// Opening price
+    pub open: f64,
+    /// Highest price
+    pub high: f64,
+    /// Lowest price
+    pub low: f64,
+    /// Closing price
+    pub close: f64,
+    /// Traded volume
+    pub volume: f64,
+    /// Dividend per share going ex on this day
+    pub ex_dividend: Option<f64>,
+    /// Ratio of a split taking effect on this day, e.g. `2.0` for a 2-for-1 split
+    pub split_ratio: Option<f64>,
+    /// Opening price adjusted for splits and dividends
+    pub adj_open: Option<f64>,
+    /// Highest price adjusted for splits and dividends
+    pub adj_high: Option<f64>,
+    /// Lowest price adjusted for splits and dividends
+    pub adj_low: Option<f64>,
+    /// Closing price adjusted for splits and dividends
+    pub adj_close: Option<f64>,
+    /// Volume adjusted for splits
+    pub adj_volume: Option<f64>,
+}
+
+impl QuandlRow for Bar {
+    /// Recognizes the column layouts of `WIKI`, `EOD` and similar databases. Names are matched
+    /// ignoring case.
+    fn layout(column_names: &[String]) -> Result<RowLayout> {
+        RowLayout::with_alternatives(column_names, COLUMNS)
+    }
+
+    fn decode(layout: &RowLayout, row: &Row) -> Result<Bar> {
+        Ok(Bar {
+            date: row.date,
+            open: try!(layout.get(0, row)),
+            high: try!(layout.get(1, row)),
+            low: try!(layout.get(2, row)),
+            close: try!(layout.get(3, row)),
+            volume: try!(layout.get(4, row)),
+            ex_dividend: try!(layout.get(5, row)),
+            split_ratio: try!(layout.get(6, row)),
+            adj_open: try!(layout.get(7, row)),
+            adj_high: try!(layout.get(8, row)),
+            adj_low: try!(layout.get(9, row)),
+            adj_close: try!(layout.get(10, row)),
+            adj_volume: try!(layout.get(11, row)),
+        })
+    }
+}
+
+/// Decode the rows of an equity price dataset into bars. Rows with a missing open, high, low,
+/// close or volume, which some databases have for days without trading, are skipped instead of
+/// failing the whole dataset.
+pub fn decode_bars(dataset: &Dataset) -> Result<Vec<Bar>> {
+    let layout = try!(Bar::layout(&dataset.column_names));
+    dataset.rows
+           .iter()
+           .filter(|row| (0..5).all(|field| layout.value(field, row).is_some()))
+           .map(|row| Bar::decode(&layout, row))
+           .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode_bars;
+    use super::super::{Bar, Dataset, NaiveDate, Row, Quandl};
+
+    fn dataset(column_names: &[&str], values: Vec<Option<f64>>) -> Dataset {
+        Dataset {
+            column_names: column_names.iter().map(|name| String::from(*name)).collect(),
+            rows: vec![Row::new(NaiveDate::from_ymd(2014, 6, 9), values)],
+        }
+    }
+
+    fn bar() -> Bar {
+        Bar {
+            date: NaiveDate::from_ymd(2014, 6, 9),
+            open: 92.7,
+            high: 93.88,
+            low: 91.75,
+            close: 93.7,
+            volume: 75415000.0,
+            ex_dividend: Some(0.0),
+            split_ratio: Some(7.0),
+            adj_open: Some(86.5),
+            adj_high: Some(87.5),
+            adj_low: Some(85.5),
+            adj_close: Some(87.25),
+            adj_volume: Some(75415000.0),
+        }
+    }
+
+    fn values() -> Vec<Option<f64>> {
+        vec![Some(92.7),
+             Some(93.88),
+             Some(91.75),
+             Some(93.7),
+             Some(75415000.0),
+             Some(0.0),
+             Some(7.0),
+             Some(86.5),
+             Some(87.5),
+             Some(85.5),
+             Some(87.25),
+             Some(75415000.0)]
+    }
+
+    #[test]
+    fn test_wiki_layout() {
+        let dataset = dataset(&["Date", "Open", "High", "Low", "Close", "Volume", "Ex-Dividend",
+                                "Split Ratio", "Adj. Open", "Adj. High", "Adj. Low",
+                                "Adj. Close", "Adj. Volume"],
+                              values());
+        assert_eq!(dataset.decode_rows::<Bar>().unwrap(), vec![bar()]);
+    }
+
+    #[test]
+    fn test_eod_layout() {
+        let dataset = dataset(&["Date", "Open", "High", "Low", "Close", "Volume", "Dividend",
+                                "Split", "Adj_Open", "Adj_High", "Adj_Low", "Adj_Close",
+                                "Adj_Volume"],
+                              values());
+        assert_eq!(dataset.decode_rows::<Bar>().unwrap(), vec![bar()]);
+    }
+
+    #[test]
+    fn test_unadjusted_layout() {
+        let unadjusted = dataset(&["Date", "open", "high", "low", "Last",
+                                   "Total Trade Quantity"],
+                                 values()[..5].to_vec());
+        assert_eq!(unadjusted.decode_rows::<Bar>().unwrap(),
+                   vec![Bar {
+                            ex_dividend: None,
+                            split_ratio: None,
+                            adj_open: None,
+                            adj_high: None,
+                            adj_low: None,
+                            adj_close: None,
+                            adj_volume: None,
+                            ..bar()
+                        }]);
+
+        let no_close = dataset(&["Date", "Open", "High", "Low", "Volume"],
+                               vec![Some(1.0), Some(1.0), Some(1.0), Some(1.0)]);
+        let err = no_close.decode_rows::<Bar>().unwrap_err().to_string();
+        assert!(err.contains("missing columns `Close`"), err);
+    }
+
+    #[test]
+    fn test_decode_bars() {
+        let mut dataset = dataset(&["Date", "Open", "High", "Low", "Close", "Volume",
+                                    "Ex-Dividend", "Split Ratio", "Adj. Open", "Adj. High",
+                                    "Adj. Low", "Adj. Close", "Adj. Volume"],
+                                  values());
+        let mut null_volume = values();
+        null_volume[4] = None;
+        dataset.rows.push(Row::new(NaiveDate::from_ymd(2014, 6, 6), null_volume));
+        let mut null_adjusted = values();
+        null_adjusted[10] = None;
+        dataset.rows.push(Row::new(NaiveDate::from_ymd(2014, 6, 5), null_adjusted));
+
+        // the row without a volume is skipped, missing adjusted values are kept as `None`
+        assert!(dataset.decode_rows::<Bar>().is_err());
+        let bars = decode_bars(&dataset).unwrap();
+        assert_eq!(bars.len(), 2);
+        assert_eq!(bars[0], bar());
+        assert_eq!(bars[1].date, NaiveDate::from_ymd(2014, 6, 5));
+        assert_eq!(bars[1].adj_close, None);
+
+        dataset.column_names.truncate(3);
+        assert!(decode_bars(&dataset).is_err());
+    }
+
+    #[test]
+    fn test_bars_column_index() {
+        // rejected before sending the request
+        let err = Quandl::new().bars("WIKI/AAPL.4").unwrap_err().to_string();
+        assert!(err.contains("selects column 4"), err);
+    }
+}
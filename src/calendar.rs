@@ -0,0 +1,342 @@
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+use chrono::{Datelike, Weekday};
+use date::DateBound;
+use error::{Error, Result};
+use quandl_request::DateInput;
+use resample;
+use super::{NaiveDate, Collapse, QuandlRequest};
+
+const NYSE: &'static str = include_str!("../calendars/nyse.txt");
+const LSE: &'static str = include_str!("../calendars/lse.txt");
+
+const WEEKDAYS: [Weekday; 7] = [Weekday::Mon,
+                                Weekday::Tue,
+                                Weekday::Wed,
+                                Weekday::Thu,
+                                Weekday::Fri,
+                                Weekday::Sat,
+                                Weekday::Sun];
+
+/// The business days of a market: the days that are neither weekend days nor holidays.
+///
+/// Calendars can be read from a file with `load`, `from_reader` or `parse`. Each line holds a
+/// holiday as a `yyyy-mm-dd` date followed by an optional description, or the weekend days as
+/// `weekend: Sat, Sun`. Text after `#` is ignored. Without a `weekend` line, the weekend is
+/// Saturday and Sunday.
+///
+/// ```ignore
+/// # New York Stock Exchange
+/// weekend: Sat, Sun
+/// 2015-01-01 New Year's Day
+/// 2015-01-19 Martin Luther King, Jr. Day
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Calendar {
+    weekend: Vec<Weekday>,
+    holidays: BTreeSet<NaiveDate>,
+}
+
+impl Default for Calendar {
+    fn default() -> Calendar {
+        Calendar::new()
+    }
+}
+
+impl Calendar {
+    /// A calendar with a Saturday and Sunday weekend and no holidays.
+    pub fn new() -> Calendar {
+        Calendar {
+            weekend: vec![Weekday::Sat, Weekday::Sun],
+            holidays: BTreeSet::new(),
+        }
+    }
+
+    /// Full-day closures of the New York Stock Exchange from 2000 to 2026, read from
+    /// `calendars/nyse.txt`.
+    pub fn nyse() -> Calendar {
+        NYSE.parse().expect("invalid calendars/nyse.txt")
+    }
+
+    /// Full-day closures of the London Stock Exchange from 2000 to 2026, read from
+    /// `calendars/lse.txt`.
+    pub fn lse() -> Calendar {
+        LSE.parse().expect("invalid calendars/lse.txt")
+    }
+
+    /// Read a calendar from the file at `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Calendar> {
+        Calendar::from_reader(&mut try!(File::open(path)))
+    }
+
+    /// Read a calendar from `reader`.
+    pub fn from_reader<R: Read>(reader: &mut R) -> Result<Calendar> {
+        let mut text = String::new();
+        try!(reader.read_to_string(&mut text));
+        text.parse()
+    }
+
+    /// Sets the weekend days.
+    ///
+    /// # Panics
+    ///
+    /// If every day of the week is a weekend day.
+    pub fn weekend(mut self, weekend: &[Weekday]) -> Calendar {
+        assert!(WEEKDAYS.iter().any(|day| !weekend.contains(day)),
+                "a calendar needs at least one working day in the week");
+        self.weekend = weekend.to_vec();
+        self
+    }
+
+    /// Adds holidays.
+    pub fn holidays<I: IntoIterator<Item = NaiveDate>>(mut self, holidays: I) -> Calendar {
+        self.holidays.extend(holidays);
+        self
+    }
+
+    /// Whether `date` falls on a weekend day.
+    pub fn is_weekend(&self, date: NaiveDate) -> bool {
+        self.weekend.contains(&date.weekday())
+    }
+
+    /// Whether `date` is a holiday.
+    pub fn is_holiday(&self, date: NaiveDate) -> bool {
+        self.holidays.contains(&date)
+    }
+
+    /// Whether `date` is neither a weekend day nor a holiday.
+    pub fn is_business_day(&self, date: NaiveDate) -> bool {
+        !self.is_weekend(date) && !self.is_holiday(date)
+    }
+
+    /// The first business day on or after `date`.
+    pub fn following(&self, date: NaiveDate) -> NaiveDate {
+        let mut date = date;
+        while !self.is_business_day(date) {
+            date = date.succ();
+        }
+        date
+    }
+
+    /// The last business day on or before `date`.
+    pub fn preceding(&self, date: NaiveDate) -> NaiveDate {
+        let mut date = date;
+        while !self.is_business_day(date) {
+            date = date.pred();
+        }
+        date
+    }
+
+    /// Move `days` business days after `date`, or before it when `days` is negative. `date`
+    /// itself need not be a business day: one business day after a Saturday is the next Monday
+    /// with a Saturday and Sunday weekend. Zero days returns `date`.
+    pub fn add_business_days(&self, date: NaiveDate, days: i64) -> NaiveDate {
+        let mut date = date;
+        for _ in 0..days.abs() {
+            date = if days > 0 {
+                self.following(date.succ())
+            } else {
+                self.preceding(date.pred())
+            };
+        }
+        date
+    }
+
+    /// The business days from `start` to `end`, both included.
+    pub fn business_days(&self, start: NaiveDate, end: NaiveDate) -> Vec<NaiveDate> {
+        let mut days = Vec::new();
+        let mut date = self.following(start);
+        while date <= end {
+            days.push(date);
+            date = self.following(date.succ());
+        }
+        days
+    }
+
+    /// Last business day of the period of `collapse` containing `date`, like
+    /// `resample::period_end` but skipping weekends and holidays at the end of the period.
+    /// `Collapse::None` and `Collapse::Daily` keep every date.
+    pub fn period_end(&self, date: NaiveDate, collapse: Collapse) -> NaiveDate {
+        match collapse {
+            Collapse::None | Collapse::Daily => date,
+            _ => self.preceding(resample::period_end(date, collapse)),
+        }
+    }
+
+    /// The last business day on or before `date`, to use as a `start_date` or `end_date`, e.g.
+    /// the last trading day with `RelativeDate::Today`.
+    pub fn business_day<D: Into<DateBound>>(&self, date: D) -> BusinessDay {
+        BusinessDay {
+            calendar: self,
+            date: date.into(),
+            offset: 0,
+        }
+    }
+}
+
+/// Parses the format described in `Calendar`.
+impl FromStr for Calendar {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Calendar> {
+        let mut calendar = Calendar::new();
+        for (number, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with("weekend:") {
+                let names = line["weekend:".len()..].split(|c: char| c == ',' || c.is_whitespace());
+                let mut weekend = Vec::new();
+                for name in names.filter(|name| !name.is_empty()) {
+                    weekend.push(try!(parse_weekday(name).ok_or_else(|| {
+                        Error::Parse(format!("invalid weekday `{}` on line {}", name, number + 1))
+                    })));
+                }
+                if WEEKDAYS.iter().all(|day| weekend.contains(day)) {
+                    return Err(Error::Parse(format!("no working day in the weekend on line {}",
+                                                    number + 1)));
+                }
+                calendar.weekend = weekend;
+            } else {
+                let date = line.split_whitespace().next().unwrap_or("");
+                calendar.holidays.insert(try!(date.parse::<NaiveDate>().map_err(|_| {
+                    Error::Parse(format!("invalid holiday `{}` on line {}", date, number + 1))
+                })));
+            }
+        }
+        Ok(calendar)
+    }
+}
+
+/// English name or three letter abbreviation of a weekday, ignoring case.
+fn parse_weekday(name: &str) -> Option<Weekday> {
+    let lower = name.to_lowercase();
+    let names = ["monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday"];
+    names.iter()
+         .position(|day| lower == *day || (lower.len() == 3 && day.starts_with(&lower[..])))
+         .map(|i| WEEKDAYS[i])
+}
+
+/// A business day of a calendar as a `start_date` or `end_date`, created with
+/// `Calendar::business_day`. The date is resolved with the clock of the `Quandl` client, moved
+/// back to the last business day on or before it, then moved by `offset` business days.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BusinessDay<'c> {
+    calendar: &'c Calendar,
+    date: DateBound,
+    offset: i64,
+}
+
+impl<'c> BusinessDay<'c> {
+    /// Move the date by `days` business days, backwards when negative.
+    pub fn offset(mut self, days: i64) -> BusinessDay<'c> {
+        self.offset = days;
+        self
+    }
+
+    /// Resolve the business day against `today`. Fails like `DateBound::resolve`.
+    pub fn resolve(&self, today: NaiveDate) -> Result<NaiveDate> {
+        let date = self.calendar.preceding(try!(self.date.resolve(today)));
+        Ok(self.calendar.add_business_days(date, self.offset))
+    }
+}
+
+impl<'c> DateInput for BusinessDay<'c> {
+    fn set_start_date(&self, quandl_request: &mut QuandlRequest) -> Result<()> {
+        try!(self.resolve(quandl_request.quandl.clock.today())).set_start_date(quandl_request)
+    }
+    fn set_end_date(&self, quandl_request: &mut QuandlRequest) -> Result<()> {
+        try!(self.resolve(quandl_request.quandl.clock.today())).set_end_date(quandl_request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Weekday;
+    use super::*;
+    use super::super::{NaiveDate, Collapse, Quandl, FixedClock, RelativeDate};
+
+    fn ymd(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd(y, m, d)
+    }
+
+    #[test]
+    fn test_exchanges() {
+        let nyse = Calendar::nyse();
+        let lse = Calendar::lse();
+        // Independence Day on a Saturday is observed on Friday
+        assert!(nyse.is_holiday(ymd(2015, 7, 3)));
+        assert!(nyse.is_business_day(ymd(2015, 12, 28)));
+        // Boxing Day on a Saturday is observed on Monday
+        assert!(lse.is_holiday(ymd(2015, 12, 28)));
+        assert!(lse.is_business_day(ymd(2015, 7, 3)));
+        assert!(nyse.is_weekend(ymd(2015, 7, 4)));
+        assert!(!nyse.is_holiday(ymd(2015, 7, 4)));
+    }
+
+    #[test]
+    fn test_business_days() {
+        let nyse = Calendar::nyse();
+        let lse = Calendar::lse();
+        // Good Friday 2015 is 2015-04-03, Easter Monday is 2015-04-06
+        assert_eq!(nyse.add_business_days(ymd(2015, 4, 2), 1), ymd(2015, 4, 6));
+        assert_eq!(lse.add_business_days(ymd(2015, 4, 2), 1), ymd(2015, 4, 7));
+        assert_eq!(lse.add_business_days(ymd(2015, 4, 7), -2), ymd(2015, 4, 1));
+        assert_eq!(nyse.add_business_days(ymd(2015, 4, 4), 0), ymd(2015, 4, 4));
+        assert_eq!(nyse.add_business_days(ymd(2015, 4, 4), -1), ymd(2015, 4, 2));
+        assert_eq!(nyse.following(ymd(2015, 4, 3)), ymd(2015, 4, 6));
+        assert_eq!(lse.preceding(ymd(2015, 4, 6)), ymd(2015, 4, 2));
+        assert_eq!(nyse.business_days(ymd(2015, 4, 2), ymd(2015, 4, 7)),
+                   vec![ymd(2015, 4, 2), ymd(2015, 4, 6), ymd(2015, 4, 7)]);
+        assert!(lse.business_days(ymd(2015, 4, 3), ymd(2015, 4, 6)).is_empty());
+    }
+
+    #[test]
+    fn test_period_end() {
+        let nyse = Calendar::nyse();
+        assert_eq!(nyse.period_end(ymd(2015, 5, 12), Collapse::Monthly), ymd(2015, 5, 29));
+        assert_eq!(nyse.period_end(ymd(2015, 4, 1), Collapse::Weekly), ymd(2015, 4, 2));
+        assert_eq!(nyse.period_end(ymd(2015, 2, 1), Collapse::Quarterly), ymd(2015, 3, 31));
+        assert_eq!(nyse.period_end(ymd(2015, 4, 4), Collapse::Daily), ymd(2015, 4, 4));
+    }
+
+    #[test]
+    fn test_parse() {
+        let calendar: Calendar = "# Friday and Saturday weekend\n\nweekend: fri, Saturday\n\
+                                  2015-01-01 New Year's Day # observed\n2015-01-04\n"
+                                     .parse()
+                                     .unwrap();
+        assert_eq!(calendar,
+                   Calendar::new()
+                       .weekend(&[Weekday::Fri, Weekday::Sat])
+                       .holidays(vec![ymd(2015, 1, 4), ymd(2015, 1, 1)]));
+        assert!(calendar.is_business_day(ymd(2015, 1, 5)));
+        assert!(!calendar.is_business_day(ymd(2015, 1, 2)));
+        assert_eq!("".parse::<Calendar>().unwrap(), Calendar::new());
+
+        let err = "2015-01-01\n2015-13-01 Nothing".parse::<Calendar>().unwrap_err().to_string();
+        assert!(err.contains("`2015-13-01` on line 2"), err);
+        let err = "weekend: Sat, Sunny".parse::<Calendar>().unwrap_err().to_string();
+        assert!(err.contains("`Sunny` on line 1"), err);
+        assert!("weekend: mon tue wed thu fri sat sun".parse::<Calendar>().is_err());
+    }
+
+    #[test]
+    fn test_business_day_input() {
+        let nyse = Calendar::nyse();
+        // 2015-04-05 is Easter Sunday
+        let q = Quandl::new().clock(FixedClock(ymd(2015, 4, 5)));
+        let qr = q.new_request("WIKI", "AAPL")
+                  .end_date(&nyse.business_day(RelativeDate::Today))
+                  .unwrap();
+        assert_eq!(qr.end_date, Some(ymd(2015, 4, 2)));
+        let qr = q.new_request("WIKI", "AAPL")
+                  .start_date(&nyse.business_day(ymd(2015, 1, 1)).offset(-1))
+                  .unwrap();
+        assert_eq!(qr.start_date, Some(ymd(2014, 12, 30)));
+    }
+}
@@ -0,0 +1,416 @@
+use std::cmp;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use chrono::{Datelike, Duration};
+use crossbeam;
+use serde::{ser, de, Serialize, Serializer, Deserialize, Deserializer};
+use date::add_months;
+use serde_util;
+use error::{Error, Result};
+use super::{NaiveDate, JsonValue, QuandlRequest, Order};
+
+/// Size of the windows a long date range is split into.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChunkWindow {
+    /// Windows of `n` days
+    Days(u32),
+    /// Windows of `n` months
+    Months(u32),
+    /// Windows of `n` years
+    Years(u32),
+}
+
+impl ChunkWindow {
+    /// Number of days, months or years in each window.
+    pub fn size(&self) -> u32 {
+        match *self {
+            ChunkWindow::Days(n) | ChunkWindow::Months(n) | ChunkWindow::Years(n) => n,
+        }
+    }
+
+    /// Start of the `i`th window of a range starting at `start`. Fails when the date is out of
+    /// the range of `NaiveDate`.
+    fn window_start(&self, start: NaiveDate, i: u32) -> Result<NaiveDate> {
+        let offset = (self.size() as i64).checked_mul(i as i64);
+        let date = match *self {
+            ChunkWindow::Days(_) => {
+                offset.and_then(|days| days.checked_add(start.num_days_from_ce() as i64))
+                      .and_then(|days| {
+                          if days > ::std::i32::MAX as i64 {
+                              None
+                          } else {
+                              NaiveDate::from_num_days_from_ce_opt(days as i32)
+                          }
+                      })
+            }
+            ChunkWindow::Months(_) => offset.and_then(|months| shift_months(start, months)),
+            ChunkWindow::Years(_) => {
+                offset.and_then(|years| years.checked_mul(12))
+                      .and_then(|months| shift_months(start, months))
+            }
+        };
+        date.ok_or_else(|| {
+            Error::Date(format!("window {} of a range starting on `{}` split into windows of `{}` \
+                                 is out of the supported date range",
+                                i,
+                                start,
+                                self))
+        })
+    }
+
+    /// Split the inclusive range `start..end` into consecutive, non-overlapping inclusive ranges
+    /// of this window size. Windows are aligned to `start` and the last one is cut at `end`.
+    /// Fails with `Error::Date` when a window would end outside the range of `NaiveDate`, e.g.
+    /// for windows of billions of days.
+    pub fn split(&self, start: NaiveDate, end: NaiveDate) -> Result<Vec<(NaiveDate, NaiveDate)>> {
+        let mut windows = Vec::new();
+        if self.size() == 0 {
+            return Ok(windows);
+        }
+        let mut i = 0;
+        let mut window_start = start;
+        while window_start <= end {
+            let next_start = try!(self.window_start(start, i + 1));
+            windows.push((window_start, cmp::min(next_start - Duration::days(1), end)));
+            window_start = next_start;
+            i += 1;
+        }
+        Ok(windows)
+    }
+}
+
+/// `add_months` with a number of months that may not fit in an `i32`, or `None` when the result
+/// is out of the range of `NaiveDate`.
+fn shift_months(date: NaiveDate, months: i64) -> Option<NaiveDate> {
+    if months > ::std::i32::MAX as i64 {
+        return None;
+    }
+    add_months(date, months as i32).ok()
+}
+
+/// Formats the window as `30d`, `6m` or `1y`.
+impl Display for ChunkWindow {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            ChunkWindow::Days(n) => write!(f, "{}d", n),
+            ChunkWindow::Months(n) => write!(f, "{}m", n),
+            ChunkWindow::Years(n) => write!(f, "{}y", n),
+        }
+    }
+}
+
+/// Parses windows like `30d`, `6m` or `1y`.
+impl FromStr for ChunkWindow {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<ChunkWindow> {
+        let invalid = || {
+            Error::Parse(format!("invalid chunk window `{}`: expected a number of days, months \
+                                  or years like `30d`, `6m` or `1y`",
+                                 s))
+        };
+        if s.is_empty() || !s.is_char_boundary(s.len() - 1) {
+            return Err(invalid());
+        }
+        let (n, unit) = s.split_at(s.len() - 1);
+        let n = try!(n.parse::<u32>().map_err(|_| invalid()));
+        match unit {
+            "d" => Ok(ChunkWindow::Days(n)),
+            "m" => Ok(ChunkWindow::Months(n)),
+            "y" => Ok(ChunkWindow::Years(n)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Split the date range of a request into windows that are fetched separately and stitched back
+/// together. See `QuandlRequest::chunking`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Chunking {
+    /// Size of each window
+    pub window: ChunkWindow,
+    /// Maximum number of windows fetched at the same time. `1` fetches them sequentially.
+    pub parallelism: usize,
+}
+
+impl Chunking {
+    /// Fetch the windows one after the other.
+    pub fn sequential(window: ChunkWindow) -> Chunking {
+        Chunking {
+            window: window,
+            parallelism: 1,
+        }
+    }
+
+    /// Fetch up to `parallelism` windows at the same time.
+    pub fn parallel(window: ChunkWindow, parallelism: usize) -> Chunking {
+        Chunking {
+            window: window,
+            parallelism: parallelism,
+        }
+    }
+}
+
+/// Send one request per window of `request`'s date range and stitch the results back together.
+/// The request is expected to be validated, so both dates are set.
+pub fn run_chunked(request: &QuandlRequest, chunking: &Chunking) -> Result<JsonValue> {
+    let (start_date, end_date) = match (request.start_date, request.end_date) {
+        (Some(start_date), Some(end_date)) => (start_date, end_date),
+        _ => return Err(Error::Date(String::from("chunking requires a start and end date"))),
+    };
+
+    let windows = try!(chunking.window.split(start_date, end_date));
+    let requests: Vec<QuandlRequest> = windows.into_iter()
+                                              .map(|(start, end)| {
+                                                  QuandlRequest {
+                                                      start_date: Some(start),
+                                                      end_date: Some(end),
+                                                      chunking: None,
+                                                      ..request.clone()
+                                                  }
+                                              })
+                                              .collect();
+
+    let mut responses = Vec::with_capacity(requests.len());
+    for batch in requests.chunks(cmp::max(chunking.parallelism, 1)) {
+        if batch.len() == 1 {
+            responses.push(try!(batch[0].run()));
+            continue;
+        }
+        let results: Vec<Result<JsonValue>> = crossbeam::scope(|scope| {
+            let handles: Vec<_> = batch.iter().map(|req| scope.spawn(move || req.run())).collect();
+            handles.into_iter().map(|handle| handle.join()).collect()
+        });
+        for result in results {
+            responses.push(try!(result));
+        }
+    }
+
+    stitch(responses, start_date, end_date, request.order)
+}
+
+/// Merge the `dataset_data` of chronologically ordered chunk responses. Rows are deduplicated
+/// by date, keeping the row of the later chunk, and sorted in the requested order (descending
+/// by default, like Quandl).
+fn stitch(responses: Vec<JsonValue>,
+          start_date: NaiveDate,
+          end_date: NaiveDate,
+          order: Option<Order>)
+          -> Result<JsonValue> {
+    let unexpected = || Error::Parse(String::from("unexpected response format: expected an \
+                                                   object with `dataset_data`"));
+    let mut dataset_data: Option<BTreeMap<String, JsonValue>> = None;
+    let mut rows: BTreeMap<String, JsonValue> = BTreeMap::new();
+
+    for response in responses {
+        let mut chunk_data = match response {
+            JsonValue::Object(mut map) => {
+                match map.remove("dataset_data") {
+                    Some(JsonValue::Object(chunk_data)) => chunk_data,
+                    _ => return Err(unexpected()),
+                }
+            }
+            _ => return Err(unexpected()),
+        };
+
+        if let Some(JsonValue::Array(data)) = chunk_data.remove("data") {
+            for row in data {
+                let date = match row.as_array()
+                                    .and_then(|row| row.get(0))
+                                    .and_then(|date| date.as_string()) {
+                    Some(date) => String::from(date),
+                    None => return Err(unexpected()),
+                };
+                rows.insert(date, row);
+            }
+        }
+        if dataset_data.is_none() {
+            dataset_data = Some(chunk_data);
+        }
+    }
+
+    let mut dataset_data = match dataset_data {
+        Some(dataset_data) => dataset_data,
+        None => BTreeMap::new(),
+    };
+    let data: Vec<JsonValue> = match order {
+        Some(Order::Asc) => rows.into_iter().map(|(_, row)| row).collect(),
+        _ => rows.into_iter().rev().map(|(_, row)| row).collect(),
+    };
+    dataset_data.insert(String::from("data"), JsonValue::Array(data));
+    dataset_data.insert(String::from("start_date"),
+                        JsonValue::String(start_date.to_string()));
+    dataset_data.insert(String::from("end_date"), JsonValue::String(end_date.to_string()));
+
+    let mut response = BTreeMap::new();
+    response.insert(String::from("dataset_data"), JsonValue::Object(dataset_data));
+    Ok(JsonValue::Object(response))
+}
+
+const FIELDS: &'static [&'static str] = &["window", "parallelism"];
+
+/// Serialized as `{"window": "1y", "parallelism": 1}`.
+impl Serialize for Chunking {
+    fn serialize<S>(&self, serializer: &mut S) -> ::std::result::Result<(), S::Error>
+        where S: Serializer
+    {
+        serializer.visit_struct("Chunking",
+                                ChunkingMapVisitor {
+                                    chunking: self,
+                                    state: 0,
+                                })
+    }
+}
+
+struct ChunkingMapVisitor<'a> {
+    chunking: &'a Chunking,
+    state: usize,
+}
+
+impl<'a> ser::MapVisitor for ChunkingMapVisitor<'a> {
+    fn visit<S>(&mut self, serializer: &mut S) -> ::std::result::Result<Option<()>, S::Error>
+        where S: Serializer
+    {
+        self.state += 1;
+        match self.state {
+            1 => {
+                let window = self.chunking.window.to_string();
+                Ok(Some(try!(serializer.visit_struct_elt("window", &window))))
+            }
+            2 => {
+                let parallelism = self.chunking.parallelism;
+                Ok(Some(try!(serializer.visit_struct_elt("parallelism", parallelism))))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn len(&self) -> Option<usize> {
+        Some(FIELDS.len())
+    }
+}
+
+impl Deserialize for Chunking {
+    fn deserialize<D>(deserializer: &mut D) -> ::std::result::Result<Chunking, D::Error>
+        where D: Deserializer
+    {
+        deserializer.visit_struct("Chunking", FIELDS, ChunkingVisitor)
+    }
+}
+
+struct ChunkingVisitor;
+
+impl de::Visitor for ChunkingVisitor {
+    type Value = Chunking;
+
+    fn visit_map<V>(&mut self, mut visitor: V) -> ::std::result::Result<Chunking, V::Error>
+        where V: de::MapVisitor
+    {
+        let mut window: Option<ChunkWindow> = None;
+        let mut parallelism: Option<usize> = None;
+
+        while let Some(key) = try!(visitor.visit_key::<String>()) {
+            match &key[..] {
+                "window" => {
+                    let s: String = try!(visitor.visit_value());
+                    window = Some(try!(serde_util::parse::<_, V::Error>(&s)));
+                }
+                "parallelism" => parallelism = Some(try!(visitor.visit_value())),
+                field => return Err(de::Error::unknown_field(field)),
+            }
+        }
+        try!(visitor.end());
+
+        match window {
+            Some(window) => Ok(Chunking::parallel(window, parallelism.unwrap_or(1))),
+            None => Err(de::Error::missing_field("window")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use super::*;
+    use super::stitch;
+    use super::super::{NaiveDate, Order};
+
+    fn ymd(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd(y, m, d)
+    }
+
+    #[test]
+    fn test_split() {
+        assert_eq!(ChunkWindow::Years(1).split(ymd(2013, 6, 1), ymd(2015, 3, 10)).unwrap(),
+                   vec![(ymd(2013, 6, 1), ymd(2014, 5, 31)),
+                        (ymd(2014, 6, 1), ymd(2015, 3, 10))]);
+        assert_eq!(ChunkWindow::Days(10).split(ymd(2015, 1, 1), ymd(2015, 1, 25)).unwrap(),
+                   vec![(ymd(2015, 1, 1), ymd(2015, 1, 10)),
+                        (ymd(2015, 1, 11), ymd(2015, 1, 20)),
+                        (ymd(2015, 1, 21), ymd(2015, 1, 25))]);
+        assert_eq!(ChunkWindow::Months(1).split(ymd(2015, 1, 31), ymd(2015, 3, 31)).unwrap(),
+                   vec![(ymd(2015, 1, 31), ymd(2015, 2, 27)),
+                        (ymd(2015, 2, 28), ymd(2015, 3, 30)),
+                        (ymd(2015, 3, 31), ymd(2015, 3, 31))]);
+        assert_eq!(ChunkWindow::Days(1).split(ymd(2015, 1, 1), ymd(2015, 1, 1)).unwrap(),
+                   vec![(ymd(2015, 1, 1), ymd(2015, 1, 1))]);
+        assert!(ChunkWindow::Days(0).split(ymd(2015, 1, 1), ymd(2015, 1, 2)).unwrap().is_empty());
+
+        // window starts past the end of `NaiveDate` are errors, not overflows
+        let (start, end) = (ymd(2015, 1, 1), ymd(2016, 1, 1));
+        assert_eq!(ChunkWindow::Years(100).split(start, end).unwrap().len(), 1);
+        assert!(ChunkWindow::Days(::std::u32::MAX).split(start, end).is_err());
+        assert!(ChunkWindow::Months(::std::u32::MAX).split(start, end).is_err());
+        assert!(ChunkWindow::Years(::std::u32::MAX).split(start, end).is_err());
+    }
+
+    #[test]
+    fn test_parse_window() {
+        assert_eq!("30d".parse::<ChunkWindow>().unwrap(), ChunkWindow::Days(30));
+        assert_eq!("6m".parse::<ChunkWindow>().unwrap(), ChunkWindow::Months(6));
+        assert_eq!("1y".parse::<ChunkWindow>().unwrap(), ChunkWindow::Years(1));
+        assert_eq!(ChunkWindow::Years(2).to_string(), "2y");
+        for s in &["", "y", "1", "1w", "-1y"] {
+            assert!(s.parse::<ChunkWindow>().is_err(), "`{}` should not parse", s);
+        }
+    }
+
+    #[test]
+    fn test_stitch() {
+        let first = serde_json::from_str("{\"dataset_data\":{\"column_names\":[\"Date\",\
+                                          \"Close\"],\"data\":[[\"2015-01-02\",2.5],\
+                                          [\"2015-01-01\",1.5]]}}")
+                        .unwrap();
+        let second = serde_json::from_str("{\"dataset_data\":{\"column_names\":[\"Date\",\
+                                           \"Close\"],\"data\":[[\"2015-01-03\",3.5],\
+                                           [\"2015-01-02\",2.25]]}}")
+                         .unwrap();
+        let stitched = stitch(vec![first, second],
+                              ymd(2015, 1, 1),
+                              ymd(2015, 1, 3),
+                              Some(Order::Asc))
+                           .unwrap();
+        assert_eq!(serde_json::to_string(&stitched).unwrap(),
+                   "{\"dataset_data\":{\"column_names\":[\"Date\",\"Close\"],\"data\":\
+                    [[\"2015-01-01\",1.5],[\"2015-01-02\",2.25],[\"2015-01-03\",3.5]],\
+                    \"end_date\":\"2015-01-03\",\"start_date\":\"2015-01-01\"}}");
+
+        let stitched = stitch(vec![], ymd(2015, 1, 1), ymd(2015, 1, 3), None).unwrap();
+        let data = stitched.lookup("dataset_data.data").unwrap();
+        assert_eq!(data.as_array().unwrap().len(), 0);
+
+        let bad = serde_json::from_str("{\"quandl_error\":{}}").unwrap();
+        assert!(stitch(vec![bad], ymd(2015, 1, 1), ymd(2015, 1, 3), None).is_err());
+    }
+
+    #[test]
+    fn test_chunking_serde() {
+        let chunking = Chunking::parallel(ChunkWindow::Years(1), 4);
+        let json = serde_json::to_string(&chunking).unwrap();
+        assert_eq!(json, "{\"window\":\"1y\",\"parallelism\":4}");
+        assert_eq!(serde_json::from_str::<Chunking>(&json).unwrap(), chunking);
+        assert_eq!(serde_json::from_str::<Chunking>("{\"window\":\"6m\"}").unwrap(),
+                   Chunking::sequential(ChunkWindow::Months(6)));
+    }
+}
@@ -0,0 +1,129 @@
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use error::{Error, Result};
+
+/// A Quandl code such as `WIKI/AAPL`, optionally selecting a column with the `WIKI/AAPL.4`
+/// syntax.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QuandlCode {
+    /// The unique database code on Quandl (ex. WIKI)
+    pub database_code: String,
+    /// The unique dataset code on Quandl (ex. APPL)
+    pub dataset_code: String,
+    /// Column selected with the `DB/DATASET.N` syntax.
+    pub column_index: Option<u64>,
+}
+
+impl QuandlCode {
+    /// Creates a `QuandlCode` for the whole dataset.
+    pub fn new(database_code: &str, dataset_code: &str) -> QuandlCode {
+        QuandlCode {
+            database_code: String::from(database_code),
+            dataset_code: String::from(dataset_code),
+            column_index: None,
+        }
+    }
+}
+
+/// Check that a database or dataset code is not empty and only contains ASCII letters, digits
+/// and underscores.
+pub fn is_valid_code(code: &str) -> bool {
+    !code.is_empty() && code.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Parses `DB/DATASET` and `DB/DATASET.N`.
+impl FromStr for QuandlCode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<QuandlCode> {
+        let invalid = |reason: &str| {
+            Error::Parse(format!("invalid Quandl code `{}`: {}", s, reason))
+        };
+
+        let mut parts = s.splitn(2, '/');
+        let database_code = parts.next().unwrap_or("");
+        let rest = match parts.next() {
+            Some(rest) => rest,
+            None => return Err(invalid("expected `DATABASE/DATASET`")),
+        };
+
+        let mut parts = rest.splitn(2, '.');
+        let dataset_code = parts.next().unwrap_or("");
+        let column_index = match parts.next() {
+            Some(column) => {
+                match column.parse::<u64>() {
+                    Ok(column) => Some(column),
+                    Err(_) => return Err(invalid("column index must be a non-negative integer")),
+                }
+            }
+            None => None,
+        };
+
+        if !is_valid_code(database_code) {
+            return Err(invalid("database code must be non-empty and only contain letters, \
+                                digits and underscores"));
+        }
+        if !is_valid_code(dataset_code) {
+            return Err(invalid("dataset code must be non-empty and only contain letters, \
+                                digits and underscores"));
+        }
+
+        Ok(QuandlCode {
+            database_code: String::from(database_code),
+            dataset_code: String::from(dataset_code),
+            column_index: column_index,
+        })
+    }
+}
+
+/// Formats the code back into the `DB/DATASET` or `DB/DATASET.N` syntax.
+impl Display for QuandlCode {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        try!(write!(f, "{}/{}", self.database_code, self.dataset_code));
+        if let Some(column_index) = self.column_index {
+            try!(write!(f, ".{}", column_index));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::Quandl;
+
+    #[test]
+    fn test_parse_code() {
+        let code: QuandlCode = "WIKI/AAPL".parse().unwrap();
+        assert_eq!(code, QuandlCode::new("WIKI", "AAPL"));
+        assert_eq!(code.to_string(), "WIKI/AAPL");
+
+        let code: QuandlCode = "CHRIS/CME_CL1.4".parse().unwrap();
+        assert_eq!(code.database_code, "CHRIS");
+        assert_eq!(code.dataset_code, "CME_CL1");
+        assert_eq!(code.column_index, Some(4));
+        assert_eq!(code.to_string(), "CHRIS/CME_CL1.4");
+    }
+
+    #[test]
+    fn test_parse_code_err() {
+        for s in &["", "WIKI", "WIKI/", "/AAPL", "WIKI/AAPL.", "WIKI/AAPL.x", "WIKI/AAPL.-1",
+                   "WI KI/AAPL", "WIKI/AA/PL", "WIKI/AAPL.4.1", "WIKI/AAPL?api_key=x"] {
+            assert!(s.parse::<QuandlCode>().is_err(), "`{}` should not parse", s);
+        }
+    }
+
+    #[test]
+    fn test_request_for() {
+        let q = Quandl::new();
+        let qr = q.request_for("WIKI/AAPL.4").unwrap();
+        assert_eq!(qr.database_code, String::from("WIKI"));
+        assert_eq!(qr.dataset_code, String::from("AAPL"));
+        assert_eq!(qr.column_index, Some(4));
+
+        let qr = q.request_for("WIKI/AAPL").unwrap();
+        assert_eq!(qr.column_index, None);
+
+        assert!(q.request_for("WIKI").is_err());
+    }
+}
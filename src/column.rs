@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+use error::{Error, Result};
+use super::{JsonValue, QuandlRequest, Order};
+
+/// Resolve the names in `request.columns` to column indexes, using the column names cached on
+/// the `Quandl` client, and run one request per column. The single column responses are merged
+/// by date into one response with a column per requested name.
+pub fn run_named_columns(request: &QuandlRequest) -> Result<JsonValue> {
+    let column_names = try!(request.quandl.column_names(&request.database_code,
+                                                        &request.dataset_code));
+
+    let mut responses = Vec::with_capacity(request.columns.len());
+    for name in &request.columns {
+        let index = match column_names.iter().position(|column| column == name) {
+            Some(0) | None => {
+                return Err(Error::Column(format!("unknown column `{}` in `{}/{}`, available \
+                                                  columns: {}",
+                                                 name,
+                                                 request.database_code,
+                                                 request.dataset_code,
+                                                 column_names.iter()
+                                                             .skip(1)
+                                                             .cloned()
+                                                             .collect::<Vec<_>>()
+                                                             .join(", "))))
+            }
+            Some(index) => index,
+        };
+        let column_request = QuandlRequest {
+            column_index: Some(index as u64),
+            columns: Vec::new(),
+            ..request.clone()
+        };
+        responses.push(try!(column_request.run()));
+    }
+
+    if responses.len() == 1 {
+        Ok(responses.remove(0))
+    } else {
+        merge_columns(responses, request.order)
+    }
+}
+
+/// Merge single column responses by date. Dates missing from one of the responses get a `null`
+/// value for that column. The date column keeps the name of the first response.
+fn merge_columns(responses: Vec<JsonValue>, order: Option<Order>) -> Result<JsonValue> {
+    let unexpected = || {
+        Error::Parse(String::from("unexpected response format: expected `dataset_data` with \
+                                   two columns"))
+    };
+    let width = responses.len();
+    let mut dataset_data: Option<BTreeMap<String, JsonValue>> = None;
+    let mut column_names = Vec::with_capacity(width + 1);
+    let mut rows: BTreeMap<String, Vec<JsonValue>> = BTreeMap::new();
+
+    for (i, response) in responses.into_iter().enumerate() {
+        let mut column_data = match response {
+            JsonValue::Object(mut map) => {
+                match map.remove("dataset_data") {
+                    Some(JsonValue::Object(column_data)) => column_data,
+                    _ => return Err(unexpected()),
+                }
+            }
+            _ => return Err(unexpected()),
+        };
+
+        match column_data.remove("column_names") {
+            Some(JsonValue::Array(mut names)) => {
+                if names.len() != 2 {
+                    return Err(unexpected());
+                }
+                if column_names.is_empty() {
+                    column_names.push(names[0].clone());
+                }
+                column_names.push(names.remove(1));
+            }
+            _ => return Err(unexpected()),
+        }
+
+        if let Some(JsonValue::Array(data)) = column_data.remove("data") {
+            for row in data {
+                let mut row = match row {
+                    JsonValue::Array(row) => row,
+                    _ => return Err(unexpected()),
+                };
+                if row.len() != 2 {
+                    return Err(unexpected());
+                }
+                let value = row.remove(1);
+                let date = match row[0].as_string() {
+                    Some(date) => String::from(date),
+                    None => return Err(unexpected()),
+                };
+                let merged = rows.entry(date.clone()).or_insert_with(|| {
+                    let mut merged = vec![JsonValue::Null; width + 1];
+                    merged[0] = JsonValue::String(date);
+                    merged
+                });
+                merged[i + 1] = value;
+            }
+        }
+
+        if dataset_data.is_none() {
+            dataset_data = Some(column_data);
+        }
+    }
+
+    let mut dataset_data = dataset_data.unwrap_or_else(BTreeMap::new);
+    let data: Vec<JsonValue> = match order {
+        Some(Order::Asc) => rows.into_iter().map(|(_, row)| JsonValue::Array(row)).collect(),
+        _ => rows.into_iter().rev().map(|(_, row)| JsonValue::Array(row)).collect(),
+    };
+    dataset_data.insert(String::from("column_names"), JsonValue::Array(column_names));
+    dataset_data.insert(String::from("column_index"), JsonValue::Null);
+    dataset_data.insert(String::from("data"), JsonValue::Array(data));
+
+    let mut response = BTreeMap::new();
+    response.insert(String::from("dataset_data"), JsonValue::Object(dataset_data));
+    Ok(JsonValue::Object(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use super::merge_columns;
+    use super::super::{Quandl, Order, JsonValue};
+
+    #[test]
+    fn test_merge_columns() {
+        let open = serde_json::from_str("{\"dataset_data\":{\"column_index\":1,\"column_names\":\
+                                         [\"Date\",\"Open\"],\"data\":[[\"2015-01-02\",1.5],\
+                                         [\"2015-01-01\",0.5]]}}")
+                       .unwrap();
+        let close = serde_json::from_str("{\"dataset_data\":{\"column_index\":4,\
+                                          \"column_names\":[\"Date\",\"Close\"],\"data\":\
+                                          [[\"2015-01-03\",3.5],[\"2015-01-02\",2.5]]}}")
+                        .unwrap();
+        let merged = merge_columns(vec![open, close], Some(Order::Asc)).unwrap();
+        assert_eq!(serde_json::to_string(&merged).unwrap(),
+                   "{\"dataset_data\":{\"column_index\":null,\"column_names\":[\"Date\",\
+                    \"Open\",\"Close\"],\"data\":[[\"2015-01-01\",0.5,null],\
+                    [\"2015-01-02\",1.5,2.5],[\"2015-01-03\",null,3.5]]}}");
+
+        // the date column is not always called `Date`
+        let open = serde_json::from_str("{\"dataset_data\":{\"column_names\":[\"Trade Date\",\
+                                         \"Open\"],\"data\":[[\"2015-01-02\",1.5]]}}")
+                       .unwrap();
+        let close = serde_json::from_str("{\"dataset_data\":{\"column_names\":[\"Trade Date\",\
+                                          \"Close\"],\"data\":[[\"2015-01-02\",2.5]]}}")
+                        .unwrap();
+        let merged = merge_columns(vec![open, close], None).unwrap();
+        assert_eq!(merged.lookup("dataset_data.column_names").unwrap(),
+                   &serde_json::from_str::<JsonValue>("[\"Trade Date\",\"Open\",\"Close\"]")
+                        .unwrap());
+
+        let bad = serde_json::from_str("{\"dataset_data\":{\"column_names\":[\"Date\",\"Open\",\
+                                        \"Close\"],\"data\":[]}}")
+                      .unwrap();
+        assert!(merge_columns(vec![bad], None).is_err());
+    }
+
+    #[test]
+    fn test_unknown_column() {
+        let q = Quandl::new();
+        q.cache_column_names("WIKI",
+                             "AAPL",
+                             vec![String::from("Date"), String::from("Open")]);
+        assert!(q.new_request("WIKI", "AAPL").column("Adj. Close").run().is_err());
+        assert!(q.new_request("WIKI", "AAPL").column("Date").run().is_err());
+    }
+}
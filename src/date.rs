@@ -0,0 +1,396 @@
+use std::fmt::Debug;
+use std::str::FromStr;
+use chrono::{Datelike, DateTime, Duration, Local, TimeZone};
+use error::{Error, Result};
+use quandl_request::DateInput;
+use super::{NaiveDate, QuandlRequest};
+
+/// Source of the current date used to resolve relative dates. `Quandl` uses `SystemClock` by
+/// default; use `FixedClock` to keep tests deterministic.
+pub trait Clock: Debug + Send + Sync {
+    /// The current date.
+    fn today(&self) -> NaiveDate;
+}
+
+/// Clock returning the current date in the local timezone.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn today(&self) -> NaiveDate {
+        Local::today().naive_local()
+    }
+}
+
+/// Clock that always returns the same date.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FixedClock(pub NaiveDate);
+
+impl Clock for FixedClock {
+    fn today(&self) -> NaiveDate {
+        self.0
+    }
+}
+
+/// A date relative to the current date of a `Clock`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RelativeDate {
+    /// The current date
+    Today,
+    /// `n` days ago
+    Days(u32),
+    /// `n` weeks ago
+    Weeks(u32),
+    /// `n` months ago. The day is clamped to the end of the month if needed.
+    Months(u32),
+    /// `n` years ago. The day is clamped to the end of the month if needed.
+    Years(u32),
+    /// The current date minus the given duration
+    Before(Duration),
+    /// The first day of the current year (year to date)
+    StartOfYear,
+    /// The first day of the current month (month to date)
+    StartOfMonth,
+}
+
+impl RelativeDate {
+    /// Resolve the relative date against `today`. Fails with `Error::Date` when the date is
+    /// out of the range of `NaiveDate`, e.g. for `100000000d`.
+    pub fn resolve(&self, today: NaiveDate) -> Result<NaiveDate> {
+        let date = match *self {
+            RelativeDate::Today => Some(today),
+            RelativeDate::Days(n) => today.checked_sub(Duration::days(n as i64)),
+            RelativeDate::Weeks(n) => today.checked_sub(Duration::weeks(n as i64)),
+            RelativeDate::Months(n) => months_before(today, n as i64),
+            RelativeDate::Years(n) => months_before(today, n as i64 * 12),
+            RelativeDate::Before(duration) => today.checked_sub(duration),
+            RelativeDate::StartOfYear => Some(NaiveDate::from_ymd(today.year(), 1, 1)),
+            RelativeDate::StartOfMonth => {
+                Some(NaiveDate::from_ymd(today.year(), today.month(), 1))
+            }
+        };
+        date.ok_or_else(|| {
+            Error::Date(format!("relative date `{:?}` from `{}` is out of the supported date \
+                                 range",
+                                self,
+                                today))
+        })
+    }
+}
+
+/// `add_months` going back `months` months, or `None` when the result is out of range.
+fn months_before(date: NaiveDate, months: i64) -> Option<NaiveDate> {
+    if -months < ::std::i32::MIN as i64 {
+        return None;
+    }
+    add_months(date, -months as i32).ok()
+}
+
+/// Parses `today`, `ytd`, `mtd`, offsets like `30d`, `2w`, `6m` and `1y`, and phrases like
+/// `last 30 days` or `last year`. Parsing is case-insensitive.
+impl FromStr for RelativeDate {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<RelativeDate> {
+        let lower = s.trim().to_lowercase();
+        let invalid = || Error::Parse(format!("invalid relative date `{}`", s));
+
+        match &lower[..] {
+            "today" => return Ok(RelativeDate::Today),
+            "ytd" => return Ok(RelativeDate::StartOfYear),
+            "mtd" => return Ok(RelativeDate::StartOfMonth),
+            _ => {}
+        }
+
+        let (n, unit) = if lower.starts_with("last ") {
+            // `last 30 days`, `last year`
+            let words: Vec<&str> = lower[5..].split_whitespace().collect();
+            match words.len() {
+                1 => (1, words[0]),
+                2 => (try!(words[0].parse::<u32>().map_err(|_| invalid())), words[1]),
+                _ => return Err(invalid()),
+            }
+        } else {
+            // `30d`, `1y`
+            let split = lower.find(|c: char| !c.is_digit(10)).unwrap_or(lower.len());
+            if split == 0 {
+                return Err(invalid());
+            }
+            (try!(lower[..split].parse::<u32>().map_err(|_| invalid())), &lower[split..])
+        };
+
+        match unit {
+            "d" | "day" | "days" => Ok(RelativeDate::Days(n)),
+            "w" | "week" | "weeks" => Ok(RelativeDate::Weeks(n)),
+            "m" | "month" | "months" => Ok(RelativeDate::Months(n)),
+            "y" | "year" | "years" => Ok(RelativeDate::Years(n)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// One end of a `DateRange`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateBound {
+    /// A fixed date
+    Date(NaiveDate),
+    /// A date relative to the current date of the `Quandl` clock
+    Relative(RelativeDate),
+}
+
+impl DateBound {
+    /// Resolve the bound against `today`. See `RelativeDate::resolve`.
+    pub fn resolve(&self, today: NaiveDate) -> Result<NaiveDate> {
+        match *self {
+            DateBound::Date(date) => Ok(date),
+            DateBound::Relative(ref relative) => relative.resolve(today),
+        }
+    }
+}
+
+impl From<NaiveDate> for DateBound {
+    fn from(date: NaiveDate) -> DateBound {
+        DateBound::Date(date)
+    }
+}
+
+impl From<RelativeDate> for DateBound {
+    fn from(relative: RelativeDate) -> DateBound {
+        DateBound::Relative(relative)
+    }
+}
+
+/// Parses either a `yyyy-mm-dd` date or a relative date accepted by `RelativeDate`.
+impl FromStr for DateBound {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<DateBound> {
+        if let Ok(date) = s.parse::<NaiveDate>() {
+            return Ok(DateBound::Date(date));
+        }
+        match s.parse::<RelativeDate>() {
+            Ok(relative) => Ok(DateBound::Relative(relative)),
+            Err(_) => {
+                Err(Error::Parse(format!("invalid date `{}`: expected `yyyy-mm-dd` or a relative \
+                                          date like `30d`, `1y`, `ytd` or `last 30 days`",
+                                         s)))
+            }
+        }
+    }
+}
+
+/// A start and end date, either of which may be open or relative to the current date. Set it
+/// on a request with `QuandlRequest::date_range`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DateRange {
+    /// Start of the range, `None` for an open start
+    pub start: Option<DateBound>,
+    /// End of the range, `None` for an open end
+    pub end: Option<DateBound>,
+}
+
+impl DateRange {
+    /// Range between two fixed dates.
+    pub fn between(start: NaiveDate, end: NaiveDate) -> DateRange {
+        DateRange {
+            start: Some(DateBound::Date(start)),
+            end: Some(DateBound::Date(end)),
+        }
+    }
+
+    /// Range from `start` up to today.
+    pub fn since(start: RelativeDate) -> DateRange {
+        DateRange {
+            start: Some(DateBound::Relative(start)),
+            end: Some(DateBound::Relative(RelativeDate::Today)),
+        }
+    }
+
+    /// The trailing `duration` up to today.
+    pub fn trailing(duration: Duration) -> DateRange {
+        DateRange::since(RelativeDate::Before(duration))
+    }
+
+    /// From the first day of the current year up to today.
+    pub fn ytd() -> DateRange {
+        DateRange::since(RelativeDate::StartOfYear)
+    }
+
+    /// From the first day of the current month up to today.
+    pub fn mtd() -> DateRange {
+        DateRange::since(RelativeDate::StartOfMonth)
+    }
+
+    /// Resolve the start and end date against the current date of `clock`.
+    pub fn resolve(&self, clock: &Clock) -> Result<(Option<NaiveDate>, Option<NaiveDate>)> {
+        let today = clock.today();
+        let start = match self.start {
+            Some(bound) => Some(try!(bound.resolve(today))),
+            None => None,
+        };
+        let end = match self.end {
+            Some(bound) => Some(try!(bound.resolve(today))),
+            None => None,
+        };
+        Ok((start, end))
+    }
+}
+
+/// Parses any relative date accepted by `RelativeDate` as the range from that date up to today,
+/// e.g. `last 30 days`, `1y` or `ytd`.
+impl FromStr for DateRange {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<DateRange> {
+        Ok(DateRange::since(try!(s.parse())))
+    }
+}
+
+/// Shift `date` by `months` months (backwards when negative), clamping the day to the last day
+/// of the resulting month. Fails with `Error::Date` when the month is out of the range of
+/// `NaiveDate`.
+pub fn add_months(date: NaiveDate, months: i32) -> Result<NaiveDate> {
+    let total = date.year() as i64 * 12 + date.month0() as i64 + months as i64;
+    let year = if total >= 0 {
+        total / 12
+    } else {
+        (total - 11) / 12
+    };
+    let month = (total - year * 12) as u32 + 1;
+    let out_of_range = || {
+        Error::Date(format!("`{}` shifted by {} months is out of the supported date range",
+                            date,
+                            months))
+    };
+    // every day up to the 28th exists in a month whose first day is in range
+    if year < ::std::i32::MIN as i64 || year > ::std::i32::MAX as i64 ||
+       NaiveDate::from_ymd_opt(year as i32, month, 1).is_none() {
+        return Err(out_of_range());
+    }
+    let mut day = date.day();
+    loop {
+        if let Some(shifted) = NaiveDate::from_ymd_opt(year as i32, month, day) {
+            return Ok(shifted);
+        }
+        day -= 1;
+    }
+}
+
+impl DateInput for RelativeDate {
+    fn set_start_date(&self, quandl_request: &mut QuandlRequest) -> Result<()> {
+        try!(self.resolve(quandl_request.quandl.clock.today())).set_start_date(quandl_request)
+    }
+    fn set_end_date(&self, quandl_request: &mut QuandlRequest) -> Result<()> {
+        try!(self.resolve(quandl_request.quandl.clock.today())).set_end_date(quandl_request)
+    }
+}
+
+impl DateInput for DateBound {
+    fn set_start_date(&self, quandl_request: &mut QuandlRequest) -> Result<()> {
+        try!(self.resolve(quandl_request.quandl.clock.today())).set_start_date(quandl_request)
+    }
+    fn set_end_date(&self, quandl_request: &mut QuandlRequest) -> Result<()> {
+        try!(self.resolve(quandl_request.quandl.clock.today())).set_end_date(quandl_request)
+    }
+}
+
+/// Uses the date of the `DateTime` in its own timezone.
+impl<Tz: TimeZone> DateInput for DateTime<Tz> {
+    fn set_start_date(&self, quandl_request: &mut QuandlRequest) -> Result<()> {
+        self.date().naive_local().set_start_date(quandl_request)
+    }
+    fn set_end_date(&self, quandl_request: &mut QuandlRequest) -> Result<()> {
+        self.date().naive_local().set_end_date(quandl_request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Datelike, Duration, FixedOffset, TimeZone};
+    use super::*;
+    use super::super::{NaiveDate, Quandl};
+
+    fn ymd(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd(y, m, d)
+    }
+
+    #[test]
+    fn test_parse_relative_date() {
+        let today = ymd(2015, 3, 15);
+        let cases = [("today", ymd(2015, 3, 15)),
+                     ("30d", ymd(2015, 2, 13)),
+                     ("2w", ymd(2015, 3, 1)),
+                     ("1m", ymd(2015, 2, 15)),
+                     ("1y", ymd(2014, 3, 15)),
+                     ("YTD", ymd(2015, 1, 1)),
+                     ("mtd", ymd(2015, 3, 1)),
+                     ("last 30 days", ymd(2015, 2, 13)),
+                     ("last week", ymd(2015, 3, 8)),
+                     ("Last 2 Years", ymd(2013, 3, 15))];
+        for &(s, expected) in cases.iter() {
+            let relative: RelativeDate = s.parse().unwrap();
+            assert_eq!(relative.resolve(today).unwrap(), expected, "{}", s);
+        }
+
+        // offsets past the range of `NaiveDate` are errors, not overflows
+        for s in &["100000000d", "50000000w", "1000000y", "4000000000m", "4000000000y"] {
+            let relative: RelativeDate = s.parse().unwrap();
+            assert!(relative.resolve(today).is_err(), "`{}` should not resolve", s);
+        }
+        assert!("5000000w".parse::<RelativeDate>().unwrap().resolve(today).unwrap().year() < 0);
+        assert!(RelativeDate::Before(Duration::days(1 << 30)).resolve(today).is_err());
+
+        for s in &["", "d", "30", "30x", "last", "last x days", "last 1 2 days", "-1d"] {
+            assert!(s.parse::<RelativeDate>().is_err(), "`{}` should not parse", s);
+        }
+    }
+
+    #[test]
+    fn test_add_months() {
+        assert_eq!(add_months(ymd(2015, 8, 31), -6).unwrap(), ymd(2015, 2, 28));
+        assert_eq!(add_months(ymd(2016, 3, 31), -1).unwrap(), ymd(2016, 2, 29));
+        assert_eq!(add_months(ymd(2015, 1, 15), -1).unwrap(), ymd(2014, 12, 15));
+        assert_eq!(add_months(ymd(2015, 11, 30), 3).unwrap(), ymd(2016, 2, 29));
+        assert!(add_months(ymd(2015, 1, 31), ::std::i32::MIN).is_err());
+        assert!(add_months(ymd(2015, 1, 31), ::std::i32::MAX).is_err());
+    }
+
+    #[test]
+    fn test_relative_start_date() {
+        let q = Quandl::new().clock(FixedClock(ymd(2015, 3, 15)));
+        let qr = q.new_request("WIKI", "AAPL").start_date("last 30 days").unwrap();
+        assert_eq!(qr.start_date, Some(ymd(2015, 2, 13)));
+
+        let qr = q.new_request("WIKI", "AAPL").start_date(&RelativeDate::StartOfYear).unwrap();
+        assert_eq!(qr.start_date, Some(ymd(2015, 1, 1)));
+
+        assert!(q.new_request("WIKI", "AAPL").start_date("yesterday-ish").is_err());
+        assert!(q.new_request("WIKI", "AAPL").start_date("100000000d").is_err());
+        assert!(q.new_request("WIKI", "AAPL").date_range(&"1000000y".parse().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_datetime_input() {
+        let q = Quandl::new();
+        let dt = FixedOffset::east(9 * 3600).ymd(2015, 3, 10).and_hms(1, 0, 0);
+        let qr = q.new_request("WIKI", "AAPL").end_date(&dt).unwrap();
+        assert_eq!(qr.end_date, Some(ymd(2015, 3, 10)));
+    }
+
+    #[test]
+    fn test_date_range() {
+        let q = Quandl::new().clock(FixedClock(ymd(2015, 3, 15)));
+        let qr = q.new_request("WIKI", "AAPL")
+                  .date_range(&DateRange::trailing(Duration::days(10)))
+                  .unwrap();
+        assert_eq!(qr.start_date, Some(ymd(2015, 3, 5)));
+        assert_eq!(qr.end_date, Some(ymd(2015, 3, 15)));
+
+        let qr = q.new_request("WIKI", "AAPL").date_range(&"ytd".parse().unwrap()).unwrap();
+        assert_eq!(qr.start_date, Some(ymd(2015, 1, 1)));
+        assert_eq!(qr.end_date, Some(ymd(2015, 3, 15)));
+
+        let range = DateRange::between(ymd(2015, 3, 10), ymd(2015, 2, 10));
+        assert!(q.new_request("WIKI", "AAPL").date_range(&range).is_err());
+    }
+}
@@ -1,6 +1,10 @@
+use std::fmt::{self, Display, Formatter};
+use std::io;
 use hyper;
 use serde_json;
 use chrono;
+use url;
+use super::NaiveDate;
 
 /// Result type often returned from methods that can have quandl `Error`s.
 pub type Result<T> = ::std::result::Result<T, Error>;
@@ -9,6 +13,13 @@ quick_error! {
     /// Error enum for quandl lib
     #[derive(Debug)]
     pub enum Error {
+        /// IO error, e.g. while reading a response
+        Io(err: io::Error) {
+            from()
+            description("io error")
+            display("io error: {}", err)
+            cause(err)
+        }
         /// Hyper client errors
         Hyper(err: hyper::Error) {
             from()
@@ -30,11 +41,44 @@ quick_error! {
             display("chrono parse error: {}", err)
             cause(err)
         }
+        /// Url parse error
+        Url(err: url::ParseError) {
+            from()
+            description("url parse error")
+            display("url parse error: {}", err)
+            cause(err)
+        }
+        /// Error resolving a column name
+        Column(err: String) {
+            description("column error")
+            display("column error: {}", err)
+        }
         /// Quandl error returned from request
         Quandl(err: String) {
             description("quandl error")
             display("quandl error: {}", err)
         }
+        /// Error parsing a parameter from a string
+        Parse(err: String) {
+            description("parse error")
+            display("parse error: {}", err)
+        }
+        /// Problems found while validating a request before it is sent
+        Validation(errs: Vec<ValidationError>) {
+            description("invalid request")
+            display("invalid request: {}",
+                    errs.iter().map(|e| e.to_string()).collect::<Vec<_>>().join(", "))
+        }
+        /// Error converting a dataset into a data frame
+        Frame(err: String) {
+            description("data frame error")
+            display("data frame error: {}", err)
+        }
+        /// Error building a continuous futures series
+        Roll(err: String) {
+            description("roll error")
+            display("roll error: {}", err)
+        }
         /// Date error used to ensure start_date > end_date
         Date(err: String) {
             description("date error")
@@ -42,3 +86,79 @@ quick_error! {
         }
     }
 }
+
+/// A single problem found when validating a `QuandlRequest` before it is sent.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    /// The database code is empty
+    EmptyDatabaseCode,
+    /// The database code contains characters other than letters, digits and underscores
+    InvalidDatabaseCode(String),
+    /// The dataset code is empty
+    EmptyDatasetCode,
+    /// The dataset code contains characters other than letters, digits and underscores
+    InvalidDatasetCode(String),
+    /// Both `column_index` and named columns are set
+    ColumnIndexAndColumns,
+    /// Both `limit` and `rows` are set
+    LimitAndRows,
+    /// `limit` is set to zero
+    ZeroLimit,
+    /// `rows` is set to zero
+    ZeroRows,
+    /// `start_date` is after `end_date`
+    StartAfterEnd(NaiveDate, NaiveDate),
+    /// Chunking is set without both `start_date` and `end_date`
+    ChunkingWithoutDateRange,
+    /// Chunking is combined with `limit` or `rows`, which would apply to each window
+    ChunkingWithLimit,
+    /// Chunking is combined with a transform, which would be computed for each window
+    ChunkingWithTransform,
+    /// The chunk window is empty
+    EmptyChunkWindow,
+    /// Chunking is set for a method that only sends a single request
+    ChunkingUnsupported,
+    /// Columns are selected by name for a method that only sends a single request
+    ColumnsUnsupported,
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            ValidationError::EmptyDatabaseCode => write!(f, "database code is empty"),
+            ValidationError::InvalidDatabaseCode(ref code) => {
+                write!(f, "database code `{}` contains invalid characters", code)
+            }
+            ValidationError::EmptyDatasetCode => write!(f, "dataset code is empty"),
+            ValidationError::InvalidDatasetCode(ref code) => {
+                write!(f, "dataset code `{}` contains invalid characters", code)
+            }
+            ValidationError::ColumnIndexAndColumns => {
+                write!(f, "only one of `column_index` and named columns can be set")
+            }
+            ValidationError::LimitAndRows => write!(f, "only one of `limit` and `rows` can be set"),
+            ValidationError::ZeroLimit => write!(f, "`limit` must be greater than zero"),
+            ValidationError::ZeroRows => write!(f, "`rows` must be greater than zero"),
+            ValidationError::StartAfterEnd(start, end) => {
+                write!(f, "start date `{}` is after end date `{}`", start, end)
+            }
+            ValidationError::ChunkingWithoutDateRange => {
+                write!(f, "chunking requires both a start and an end date")
+            }
+            ValidationError::ChunkingWithLimit => {
+                write!(f, "chunking can't be combined with `limit` or `rows`")
+            }
+            ValidationError::ChunkingWithTransform => {
+                write!(f, "chunking can't be combined with `transform`")
+            }
+            ValidationError::EmptyChunkWindow => write!(f, "chunk window must not be empty"),
+            ValidationError::ChunkingUnsupported => {
+                write!(f, "chunking is only supported by `run`")
+            }
+            ValidationError::ColumnsUnsupported => {
+                write!(f, "columns selected by name are only supported by `run`, use \
+                           `column_index` instead")
+            }
+        }
+    }
+}
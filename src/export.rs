@@ -0,0 +1,232 @@
+use std::fmt::Write as FmtWrite;
+use std::io::Write;
+use serde_json;
+use error::{Error, Result};
+use super::{Dataset, NaiveDate};
+
+#[cfg(feature = "export-parquet")]
+use parquet::arrow::ArrowWriter;
+#[cfg(feature = "export-parquet")]
+use frame;
+
+/// Options for `write_csv`. The defaults match the CSV format of Quandl: comma separated, with
+/// dates like `2015-01-30`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvOptions {
+    /// Field delimiter
+    pub delimiter: char,
+    /// Format of the date column, as understood by `chrono::format::strftime`
+    pub date_format: String,
+}
+
+impl Default for CsvOptions {
+    fn default() -> CsvOptions {
+        CsvOptions {
+            delimiter: ',',
+            date_format: String::from("%Y-%m-%d"),
+        }
+    }
+}
+
+impl CsvOptions {
+    /// Sets the field delimiter, e.g. `;` or `\t`.
+    pub fn delimiter(mut self, delimiter: char) -> CsvOptions {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Sets the format of the date column, e.g. `%d/%m/%Y`.
+    pub fn date_format(mut self, date_format: &str) -> CsvOptions {
+        self.date_format = String::from(date_format);
+        self
+    }
+}
+
+/// Write a dataset as CSV: a header with the column names, then one line per row. Missing
+/// values are written as empty fields, and fields containing the delimiter, quotes or line
+/// breaks are quoted. Fails before writing anything if a row doesn't have one value per value
+/// column or if the date format is invalid.
+pub fn write_csv<W: Write>(dataset: &Dataset, writer: &mut W, options: &CsvOptions) -> Result<()> {
+    try!(check_widths(dataset));
+    if let Some(row) = dataset.rows.first() {
+        try!(format_date(row.date, &options.date_format));
+    }
+    let delimiter = options.delimiter.to_string();
+    let header: Vec<String> = dataset.column_names
+                                     .iter()
+                                     .map(|name| quote(name, options.delimiter))
+                                     .collect();
+    try!(writeln!(writer, "{}", header.join(&delimiter)));
+    for row in &dataset.rows {
+        let date = try!(format_date(row.date, &options.date_format));
+        let mut fields = vec![quote(&date, options.delimiter)];
+        for value in &row.values {
+            fields.push(match *value {
+                Some(value) => value.to_string(),
+                None => String::new(),
+            });
+        }
+        try!(writeln!(writer, "{}", fields.join(&delimiter)));
+    }
+    Ok(())
+}
+
+/// Write a dataset as JSON Lines: one JSON object per row, with the column names as keys in the
+/// order of the dataset. Missing and non-finite values are written as `null`. Fails before
+/// writing anything if a row doesn't have one value per value column.
+pub fn write_json_lines<W: Write>(dataset: &Dataset, writer: &mut W) -> Result<()> {
+    let keys: Vec<String> = try!(dataset.column_names
+                                        .iter()
+                                        .map(serde_json::to_string)
+                                        .collect::<::std::result::Result<_, _>>());
+    if keys.is_empty() {
+        return Err(Error::Parse(String::from("dataset has no column names")));
+    }
+    try!(check_widths(dataset));
+    for row in &dataset.rows {
+        let mut line = format!("{{{}:\"{}\"", keys[0], row.date);
+        for (key, value) in keys[1..].iter().zip(row.values.iter()) {
+            let value = match *value {
+                Some(value) if value.is_finite() => try!(serde_json::to_string(&value)),
+                _ => String::from("null"),
+            };
+            line.push_str(&format!(",{}:{}", key, value));
+        }
+        try!(writeln!(writer, "{}}}", line));
+    }
+    Ok(())
+}
+
+/// Write a dataset as a Parquet file, with the column types of `frame::to_record_batch`.
+#[cfg(feature = "export-parquet")]
+pub fn write_parquet<W>(dataset: &Dataset, writer: W) -> Result<()>
+    where W: Write + Send
+{
+    let batch = try!(frame::to_record_batch(dataset));
+    let mut writer = try!(ArrowWriter::try_new(writer, batch.schema(), None)
+                              .map_err(|err| Error::Frame(err.to_string())));
+    try!(writer.write(&batch).map_err(|err| Error::Frame(err.to_string())));
+    try!(writer.close().map_err(|err| Error::Frame(err.to_string())));
+    Ok(())
+}
+
+/// Fail if a row doesn't have exactly one value per value column, which would drop values or
+/// shift them to the wrong column.
+fn check_widths(dataset: &Dataset) -> Result<()> {
+    let width = dataset.column_names.len().saturating_sub(1);
+    match dataset.rows.iter().find(|row| row.values.len() != width) {
+        Some(row) => {
+            Err(Error::Parse(format!("row `{}` has {} values but the dataset has {} value \
+                                      columns",
+                                     row.date,
+                                     row.values.len(),
+                                     width)))
+        }
+        None => Ok(()),
+    }
+}
+
+/// Format `date` with a `strftime` format, failing instead of panicking when the format is
+/// invalid or asks for a time.
+fn format_date(date: NaiveDate, format: &str) -> Result<String> {
+    let mut formatted = String::new();
+    match write!(formatted, "{}", date.format(format)) {
+        Ok(()) => Ok(formatted),
+        Err(_) => Err(Error::Parse(format!("invalid date format `{}`", format))),
+    }
+}
+
+/// Quote a CSV field if it contains the delimiter, a quote or a line break.
+fn quote(field: &str, delimiter: char) -> String {
+    if field.contains(delimiter) || field.contains('"') || field.contains('\n') ||
+       field.contains('\r') {
+        format!("\"{}\"", field.replace("\"", "\"\""))
+    } else {
+        String::from(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use serde_json;
+    use super::*;
+    use super::super::{Dataset, NaiveDate, Row, RowIter, JsonValue};
+
+    fn dataset() -> Dataset {
+        Dataset {
+            column_names: vec![String::from("Date"),
+                               String::from("Open"),
+                               String::from("Adj. \"Close\"")],
+            rows: vec![Row::new(NaiveDate::from_ymd(2015, 1, 2), vec![Some(1.5), None]),
+                       Row::new(NaiveDate::from_ymd(2015, 1, 1), vec![Some(-2.5), Some(3.5)])],
+        }
+    }
+
+    #[test]
+    fn test_write_csv() {
+        let mut out = Vec::new();
+        write_csv(&dataset(), &mut out, &CsvOptions::default()).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        assert_eq!(csv,
+                   "Date,Open,\"Adj. \"\"Close\"\"\"\n2015-01-02,1.5,\n2015-01-01,-2.5,3.5\n");
+        // the default options give the CSV format of Quandl
+        assert_eq!(RowIter::new(Cursor::new(csv)).unwrap().into_dataset().unwrap(), dataset());
+
+        let mut out = Vec::new();
+        let options = CsvOptions::default().delimiter(';').date_format("%d/%m/%Y");
+        write_csv(&dataset(), &mut out, &options).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "Date;Open;\"Adj. \"\"Close\"\"\"\n02/01/2015;1.5;\n01/01/2015;-2.5;3.5\n");
+
+        // invalid formats and formats with a time are errors, and nothing is written
+        for format in &["%Q", "%Y-%m-%", "%Y %H:%M"] {
+            let mut out = Vec::new();
+            let options = CsvOptions::default().date_format(format);
+            assert!(write_csv(&dataset(), &mut out, &options).is_err(), "{}", format);
+            assert!(out.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_write_json_lines() {
+        let mut dataset = dataset();
+        dataset.rows[1].values[0] = Some(::std::f64::NAN);
+        let mut out = Vec::new();
+        write_json_lines(&dataset, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("{\"Date\":\"2015-01-02\",\"Open\":1.5,"));
+        assert!(lines[1].ends_with(",\"Open\":null,\"Adj. \\\"Close\\\"\":3.5}"));
+        for line in lines {
+            let row: JsonValue = serde_json::from_str(line).unwrap();
+            assert!(row.as_object().unwrap().contains_key("Adj. \"Close\""));
+        }
+
+        assert!(write_json_lines(&Dataset::default(), &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_row_widths() {
+        // rows with more or fewer values than value columns are rejected
+        let mut dataset = dataset();
+        dataset.rows[1].values.push(Some(1.0));
+        assert!(write_json_lines(&dataset, &mut Vec::new()).is_err());
+        assert!(write_csv(&dataset, &mut Vec::new(), &CsvOptions::default()).is_err());
+        dataset.rows[1].values.truncate(1);
+        let mut out = Vec::new();
+        assert!(write_json_lines(&dataset, &mut out).is_err());
+        assert!(out.is_empty());
+        assert!(write_csv(&dataset, &mut out, &CsvOptions::default()).is_err());
+        assert!(out.is_empty());
+    }
+
+    #[cfg(feature = "export-parquet")]
+    #[test]
+    fn test_write_parquet() {
+        let mut out = Vec::new();
+        write_parquet(&dataset(), &mut out).unwrap();
+        assert_eq!(&out[..4], b"PAR1");
+    }
+}
@@ -0,0 +1,130 @@
+use error::{Error, Result};
+use super::{Dataset, NaiveDate};
+
+#[cfg(feature = "arrow")]
+use std::sync::Arc;
+#[cfg(feature = "arrow")]
+use arrow::array::{ArrayRef, Date32Array, Float64Array};
+#[cfg(feature = "arrow")]
+use arrow::datatypes::{DataType, Field, Schema};
+#[cfg(feature = "arrow")]
+use arrow::record_batch::RecordBatch;
+#[cfg(feature = "polars")]
+use polars::prelude::{DataFrame, Series, NamedFrom};
+#[cfg(feature = "polars")]
+use polars::prelude::DataType as PolarsDataType;
+
+/// Convert a dataset into an Arrow `RecordBatch`. The date column becomes a non-nullable
+/// `Date32` column and every other column a nullable `Float64` column, named after the column
+/// names of the response.
+#[cfg(feature = "arrow")]
+pub fn to_record_batch(dataset: &Dataset) -> Result<RecordBatch> {
+    let (date_name, value_names) = try!(names(dataset));
+    let mut fields = vec![Field::new(date_name, DataType::Date32, false)];
+    let mut columns: Vec<ArrayRef> = vec![Arc::new(Date32Array::from(days(dataset)))];
+    for (i, name) in value_names.iter().enumerate() {
+        fields.push(Field::new(name, DataType::Float64, true));
+        columns.push(Arc::new(Float64Array::from(values(dataset, i))));
+    }
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+        .map_err(|err| Error::Frame(err.to_string()))
+}
+
+/// Convert a dataset into a Polars `DataFrame`, with the same column types as
+/// `to_record_batch`.
+#[cfg(feature = "polars")]
+pub fn to_data_frame(dataset: &Dataset) -> Result<DataFrame> {
+    let (date_name, value_names) = try!(names(dataset));
+    let dates = try!(Series::new(date_name, days(dataset))
+                         .cast(&PolarsDataType::Date)
+                         .map_err(|err| Error::Frame(err.to_string())));
+    let mut columns = vec![dates];
+    for (i, name) in value_names.iter().enumerate() {
+        columns.push(Series::new(name, values(dataset, i)));
+    }
+    DataFrame::new(columns).map_err(|err| Error::Frame(err.to_string()))
+}
+
+/// The name of the date column and the names of the value columns. Every row must fit the
+/// column names.
+fn names(dataset: &Dataset) -> Result<(&str, &[String])> {
+    let (date_name, value_names) = match dataset.column_names.split_first() {
+        Some((date_name, value_names)) => (date_name, value_names),
+        None => return Err(Error::Frame(String::from("dataset has no column names"))),
+    };
+    if let Some(row) = dataset.rows.iter().find(|row| row.values.len() > value_names.len()) {
+        return Err(Error::Frame(format!("row `{}` has {} values but the dataset has {} value \
+                                         columns",
+                                        row.date,
+                                        row.values.len(),
+                                        value_names.len())));
+    }
+    Ok((date_name, value_names))
+}
+
+/// Dates of the rows as days since the Unix epoch, the representation of `Date32`.
+fn days(dataset: &Dataset) -> Vec<i32> {
+    let epoch = NaiveDate::from_ymd(1970, 1, 1);
+    dataset.rows.iter().map(|row| (row.date - epoch).num_days() as i32).collect()
+}
+
+/// Values of the `i`th value column. Rows with fewer values have `None`.
+fn values(dataset: &Dataset, i: usize) -> Vec<Option<f64>> {
+    dataset.rows.iter().map(|row| row.values.get(i).and_then(|v| *v)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{names, days, values};
+    use super::super::{Dataset, NaiveDate, Row};
+
+    fn dataset() -> Dataset {
+        Dataset {
+            column_names: vec![String::from("Date"), String::from("Open"), String::from("Close")],
+            rows: vec![Row::new(NaiveDate::from_ymd(1970, 1, 2), vec![Some(1.5), None]),
+                       Row::new(NaiveDate::from_ymd(1969, 12, 31), vec![Some(2.5)])],
+        }
+    }
+
+    #[test]
+    fn test_columns() {
+        let dataset = dataset();
+        let (date_name, value_names) = names(&dataset).unwrap();
+        assert_eq!(date_name, "Date");
+        assert_eq!(value_names, &["Open", "Close"]);
+        assert_eq!(days(&dataset), vec![1, -1]);
+        assert_eq!(values(&dataset, 0), vec![Some(1.5), Some(2.5)]);
+        assert_eq!(values(&dataset, 1), vec![None, None]);
+
+        assert!(names(&Dataset::default()).is_err());
+        let mut wide = dataset.clone();
+        wide.rows[0].values.push(Some(3.5));
+        assert!(names(&wide).is_err());
+    }
+
+    #[cfg(feature = "arrow")]
+    #[test]
+    fn test_record_batch() {
+        use arrow::datatypes::DataType;
+        let batch = super::to_record_batch(&dataset()).unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(batch.num_columns(), 3);
+        let schema = batch.schema();
+        assert_eq!(schema.field(0).name(), "Date");
+        assert_eq!(schema.field(0).data_type(), &DataType::Date32);
+        assert_eq!(schema.field(2).name(), "Close");
+        assert_eq!(schema.field(2).data_type(), &DataType::Float64);
+        assert_eq!(batch.column(2).null_count(), 2);
+    }
+
+    #[cfg(feature = "polars")]
+    #[test]
+    fn test_data_frame() {
+        use polars::prelude::DataType;
+        let frame = super::to_data_frame(&dataset()).unwrap();
+        assert_eq!(frame.shape(), (2, 3));
+        assert_eq!(frame.get_column_names(), vec!["Date", "Open", "Close"]);
+        assert_eq!(frame.dtypes(), vec![DataType::Date, DataType::Float64, DataType::Float64]);
+        assert_eq!(frame.column("Close").unwrap().null_count(), 2);
+    }
+}
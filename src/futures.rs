@@ -0,0 +1,410 @@
+use std::collections::BTreeMap;
+use error::{Error, Result};
+use typed::{QuandlRow, RowLayout};
+use super::{NaiveDate, Row, Dataset, QuandlRequest};
+use chrono::Duration;
+
+/// Column names of the futures databases, e.g. `CME` and `ICE`, and of `CHRIS`, in order of
+/// preference.
+const COLUMNS: &'static [(&'static [&'static str], bool)] =
+    &[(&["Settle", "Last", "Close"], false),
+      (&["Volume"], true),
+      (&["Previous Day Open Interest", "Prev. Day Open Interest", "Open Interest"], true)];
+
+/// A daily row of a futures contract.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuturesBar {
+    /// Date of the row
+    pub date: NaiveDate,
+    /// Settlement price, or the last price for databases without settlement prices
+    pub settle: f64,
+    /// Traded volume
+    pub volume: Option<f64>,
+    /// Open interest
+    pub open_interest: Option<f64>,
+}
+
+impl QuandlRow for FuturesBar {
+    fn layout(column_names: &[String]) -> Result<RowLayout> {
+        RowLayout::with_alternatives(column_names, COLUMNS)
+    }
+
+    fn decode(layout: &RowLayout, row: &Row) -> Result<FuturesBar> {
+        Ok(FuturesBar {
+            date: row.date,
+            settle: try!(layout.get(0, row)),
+            volume: try!(layout.get(1, row)),
+            open_interest: try!(layout.get(2, row)),
+        })
+    }
+}
+
+/// The rows of a single futures contract, e.g. `CME/CLF2016`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contract {
+    /// Name of the contract, used in the roll log
+    pub name: String,
+    /// Last trading day of the contract
+    pub expiry: NaiveDate,
+    /// Rows of the contract in any order
+    pub bars: Vec<FuturesBar>,
+}
+
+impl Contract {
+    /// Creates a contract from its rows.
+    pub fn new(name: &str, expiry: NaiveDate, bars: Vec<FuturesBar>) -> Contract {
+        Contract {
+            name: String::from(name),
+            expiry: expiry,
+            bars: bars,
+        }
+    }
+
+    /// Fetch the rows of a contract with `request`. The contract is named after the codes of
+    /// the request.
+    pub fn fetch(request: &QuandlRequest, expiry: NaiveDate) -> Result<Contract> {
+        let bars = try!(request.run_typed());
+        let name = format!("{}/{}", request.database_code, request.dataset_code);
+        Ok(Contract::new(&name, expiry, bars))
+    }
+}
+
+/// When to roll from one contract to the next.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RollRule {
+    /// Roll on the first day both contracts trade that is at most this many calendar days before
+    /// the expiry of the front contract
+    DaysBeforeExpiry(u32),
+    /// Roll on the first day the volume of the next contract exceeds the volume of the front
+    /// contract
+    Volume,
+    /// Roll on the first day the open interest of the next contract exceeds the open interest of
+    /// the front contract
+    OpenInterest,
+}
+
+/// How prices before a roll are adjusted to remove the gap between the two contracts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Adjustment {
+    /// Keep the prices of each contract unchanged
+    None,
+    /// Add the difference between the next and the front contract on the roll date to all
+    /// earlier prices
+    Difference,
+    /// Multiply all earlier prices by the ratio of the next to the front contract on the roll
+    /// date, which keeps returns unchanged
+    Ratio,
+}
+
+/// A roll from one contract to the next. Prices of both contracts are those of the roll date.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Roll {
+    /// First date on which the next contract is used
+    pub date: NaiveDate,
+    /// Name of the front contract
+    pub from: String,
+    /// Name of the next contract
+    pub to: String,
+    /// Price of the front contract
+    pub from_price: f64,
+    /// Price of the next contract
+    pub to_price: f64,
+}
+
+/// A continuous series built by `continuous`, in ascending order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContinuousSeries {
+    /// Dates of the series
+    pub dates: Vec<NaiveDate>,
+    /// Adjusted prices
+    pub prices: Vec<f64>,
+    /// Unadjusted prices of the contract used on each date
+    pub raw_prices: Vec<f64>,
+    /// Index in the sorted contracts of the contract used on each date
+    pub contracts: Vec<usize>,
+    /// All rolls, in ascending order
+    pub rolls: Vec<Roll>,
+}
+
+impl ContinuousSeries {
+    /// A dataset with the columns `Date`, `Settle` (the adjusted price) and `Raw Settle`.
+    pub fn to_dataset(&self) -> Dataset {
+        Dataset {
+            column_names: vec![String::from("Date"),
+                               String::from("Settle"),
+                               String::from("Raw Settle")],
+            rows: self.dates
+                      .iter()
+                      .zip(self.prices.iter().zip(self.raw_prices.iter()))
+                      .map(|(&date, (&price, &raw))| Row::new(date, vec![Some(price), Some(raw)]))
+                      .collect(),
+        }
+    }
+}
+
+/// Build a continuous series from `contracts`, which are sorted by expiry first. Each contract
+/// is used from the roll onto it until the roll onto the next, chosen with `rule`. If the rule
+/// never triggers, the roll happens on the last day both contracts trade. Prices before each
+/// roll are adjusted with `adjustment`, so the latest contract keeps its own prices.
+///
+/// Fails if two consecutive contracts have no dates in common after the previous roll.
+pub fn continuous(contracts: &[Contract],
+                  rule: RollRule,
+                  adjustment: Adjustment)
+                  -> Result<ContinuousSeries> {
+    let mut contracts: Vec<&Contract> = contracts.iter().collect();
+    contracts.sort_by(|a, b| a.expiry.cmp(&b.expiry));
+    let sorted: Vec<Vec<&FuturesBar>> = contracts.iter()
+                                                 .map(|contract| {
+                                                     let mut bars: Vec<&FuturesBar> =
+                                                         contract.bars.iter().collect();
+                                                     bars.sort_by(|a, b| a.date.cmp(&b.date));
+                                                     bars
+                                                 })
+                                                 .collect();
+
+    let mut rolls: Vec<Roll> = Vec::new();
+    for i in 1..contracts.len() {
+        let after = rolls.last().map(|roll| roll.date);
+        let (date, from_price, to_price) = match roll_date(contracts[i - 1],
+                                                           &sorted[i - 1],
+                                                           &sorted[i],
+                                                           rule,
+                                                           after) {
+            Some(roll) => roll,
+            None => {
+                return Err(Error::Roll(format!("contracts `{}` and `{}` have no dates in \
+                                                common",
+                                               contracts[i - 1].name,
+                                               contracts[i].name)))
+            }
+        };
+        rolls.push(Roll {
+            date: date,
+            from: contracts[i - 1].name.clone(),
+            to: contracts[i].name.clone(),
+            from_price: from_price,
+            to_price: to_price,
+        });
+    }
+
+    let mut series = ContinuousSeries {
+        dates: Vec::new(),
+        prices: Vec::new(),
+        raw_prices: Vec::new(),
+        contracts: Vec::new(),
+        rolls: Vec::new(),
+    };
+    for (i, bars) in sorted.iter().enumerate() {
+        let start = if i == 0 {
+            None
+        } else {
+            Some(rolls[i - 1].date)
+        };
+        let end = rolls.get(i).map(|roll| roll.date);
+        for bar in bars {
+            if start.map_or(true, |start| bar.date >= start) &&
+               end.map_or(true, |end| bar.date < end) {
+                series.dates.push(bar.date);
+                series.raw_prices.push(bar.settle);
+                series.contracts.push(i);
+            }
+        }
+    }
+
+    // adjust backwards from the latest contract
+    let (mut difference, mut ratio) = (0.0, 1.0);
+    let mut next_roll = rolls.len();
+    series.prices = vec![0.0; series.raw_prices.len()];
+    for j in (0..series.dates.len()).rev() {
+        while next_roll > series.contracts[j] {
+            let roll = &rolls[next_roll - 1];
+            difference += roll.to_price - roll.from_price;
+            if roll.from_price != 0.0 {
+                ratio *= roll.to_price / roll.from_price;
+            }
+            next_roll -= 1;
+        }
+        let raw = series.raw_prices[j];
+        series.prices[j] = match adjustment {
+            Adjustment::None => raw,
+            Adjustment::Difference => raw + difference,
+            Adjustment::Ratio => raw * ratio,
+        };
+    }
+    series.rolls = rolls;
+    Ok(series)
+}
+
+/// Find the roll date from `front` to `next` after `after` with `rule`, returning the date and
+/// the prices of both contracts on that date.
+fn roll_date(front: &Contract,
+             front_bars: &[&FuturesBar],
+             next_bars: &[&FuturesBar],
+             rule: RollRule,
+             after: Option<NaiveDate>)
+             -> Option<(NaiveDate, f64, f64)> {
+    let next: BTreeMap<NaiveDate, &FuturesBar> = next_bars.iter()
+                                                          .map(|bar| (bar.date, *bar))
+                                                          .collect();
+    let mut last_common = None;
+    for bar in front_bars {
+        if after.map_or(false, |after| bar.date <= after) {
+            continue;
+        }
+        let next_bar = match next.get(&bar.date) {
+            Some(next_bar) => next_bar,
+            None => continue,
+        };
+        let roll = match rule {
+            RollRule::DaysBeforeExpiry(days) => {
+                // a window reaching past the start of `NaiveDate` includes every day
+                match front.expiry.checked_sub(Duration::days(days as i64)) {
+                    Some(start) => bar.date >= start,
+                    None => true,
+                }
+            }
+            RollRule::Volume => exceeds(next_bar.volume, bar.volume),
+            RollRule::OpenInterest => exceeds(next_bar.open_interest, bar.open_interest),
+        };
+        last_common = Some((bar.date, bar.settle, next_bar.settle));
+        if roll {
+            return last_common;
+        }
+    }
+    last_common
+}
+
+/// Whether both values are known and `next` is greater than `front`.
+fn exceeds(next: Option<f64>, front: Option<f64>) -> bool {
+    match (next, front) {
+        (Some(next), Some(front)) => next > front,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{Dataset, NaiveDate, Row};
+
+    fn ymd(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd(2015, 1, d)
+    }
+
+    fn bar(d: u32, settle: f64, volume: f64) -> FuturesBar {
+        FuturesBar {
+            date: ymd(d),
+            settle: settle,
+            volume: Some(volume),
+            open_interest: Some(volume * 10.0),
+        }
+    }
+
+    fn contracts() -> Vec<Contract> {
+        // in reverse order of expiry and with rows newest first, like Quandl
+        vec![Contract::new("CME/CLH2015",
+                           ymd(20),
+                           vec![bar(8, 54.0, 90.0),
+                                bar(7, 53.0, 80.0),
+                                bar(6, 52.0, 50.0),
+                                bar(5, 51.0, 20.0)]),
+             Contract::new("CME/CLG2015",
+                           ymd(7),
+                           vec![bar(7, 51.0, 10.0),
+                                bar(6, 50.0, 40.0),
+                                bar(5, 49.0, 60.0),
+                                bar(2, 48.0, 70.0)])]
+    }
+
+    #[test]
+    fn test_volume_roll() {
+        let series = continuous(&contracts(), RollRule::Volume, Adjustment::None).unwrap();
+        assert_eq!(series.rolls,
+                   vec![Roll {
+                            date: ymd(6),
+                            from: String::from("CME/CLG2015"),
+                            to: String::from("CME/CLH2015"),
+                            from_price: 50.0,
+                            to_price: 52.0,
+                        }]);
+        assert_eq!(series.dates, vec![ymd(2), ymd(5), ymd(6), ymd(7), ymd(8)]);
+        assert_eq!(series.contracts, vec![0, 0, 1, 1, 1]);
+        assert_eq!(series.prices, vec![48.0, 49.0, 52.0, 53.0, 54.0]);
+
+        let series = continuous(&contracts(), RollRule::OpenInterest, Adjustment::None).unwrap();
+        assert_eq!(series.rolls[0].date, ymd(6));
+    }
+
+    #[test]
+    fn test_days_before_expiry() {
+        let series = continuous(&contracts(), RollRule::DaysBeforeExpiry(1), Adjustment::None)
+                         .unwrap();
+        assert_eq!(series.rolls[0].date, ymd(6));
+        // the 5th is two days before expiry
+        let series = continuous(&contracts(), RollRule::DaysBeforeExpiry(2), Adjustment::None)
+                         .unwrap();
+        assert_eq!(series.rolls[0].date, ymd(5));
+        assert_eq!(series.contracts, vec![0, 1, 1, 1, 1]);
+
+        // windows longer than the range of dates roll on the first common day
+        let series = continuous(&contracts(),
+                                RollRule::DaysBeforeExpiry(::std::u32::MAX),
+                                Adjustment::None)
+                         .unwrap();
+        assert_eq!(series.rolls[0].date, ymd(5));
+    }
+
+    #[test]
+    fn test_adjustments() {
+        let series = continuous(&contracts(), RollRule::Volume, Adjustment::Difference).unwrap();
+        assert_eq!(series.prices, vec![50.0, 51.0, 52.0, 53.0, 54.0]);
+        assert_eq!(series.raw_prices, vec![48.0, 49.0, 52.0, 53.0, 54.0]);
+
+        let series = continuous(&contracts(), RollRule::Volume, Adjustment::Ratio).unwrap();
+        assert_eq!(series.prices[1], 49.0 * (52.0 / 50.0));
+        assert_eq!(series.prices[2], 52.0);
+
+        let dataset = series.to_dataset();
+        assert_eq!(dataset.column_names, vec!["Date", "Settle", "Raw Settle"]);
+        assert_eq!(dataset.rows[0],
+                   Row::new(ymd(2), vec![Some(48.0 * (52.0 / 50.0)), Some(48.0)]));
+    }
+
+    #[test]
+    fn test_fallback_and_errors() {
+        // volume never crosses over, so the roll is on the last common day
+        let mut contracts = contracts();
+        for bar in &mut contracts[0].bars {
+            bar.volume = Some(0.0);
+        }
+        let series = continuous(&contracts, RollRule::Volume, Adjustment::None).unwrap();
+        assert_eq!(series.rolls[0].date, ymd(7));
+
+        contracts[1].bars.truncate(0);
+        contracts[1].bars.push(bar(1, 47.0, 1.0));
+        let err = continuous(&contracts, RollRule::Volume, Adjustment::None).unwrap_err();
+        assert!(err.to_string().contains("`CME/CLG2015` and `CME/CLH2015`"));
+
+        assert!(continuous(&[], RollRule::Volume, Adjustment::None).unwrap().dates.is_empty());
+    }
+
+    #[test]
+    fn test_futures_bar_layout() {
+        let dataset = Dataset {
+            column_names: vec![String::from("Date"),
+                               String::from("Open"),
+                               String::from("Settle"),
+                               String::from("Volume"),
+                               String::from("Previous Day Open Interest")],
+            rows: vec![Row::new(ymd(5), vec![Some(1.5), Some(2.5), Some(3.0), None])],
+        };
+        assert_eq!(dataset.decode_rows::<FuturesBar>().unwrap(),
+                   vec![FuturesBar {
+                            date: ymd(5),
+                            settle: 2.5,
+                            volume: Some(3.0),
+                            open_interest: None,
+                        }]);
+    }
+}
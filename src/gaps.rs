@@ -0,0 +1,298 @@
+use std::collections::BTreeSet;
+use chrono::Duration;
+use calendar::Calendar;
+use resample::period_end;
+use super::{NaiveDate, Collapse, TimeSeries};
+
+/// The dates on which a series is expected to have a value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Frequency {
+    /// Every day
+    Daily,
+    /// Monday to Friday, the business days of `Calendar::new()`
+    BusinessDaily,
+    /// The business days of a calendar, so that holidays are not reported as gaps
+    BusinessDays(Calendar),
+    /// One value in each period of `collapse`, on any date of the period. Periods are identified
+    /// by their last day, see `resample::period_end`.
+    Period(Collapse),
+}
+
+impl Frequency {
+    /// The expected date standing for `date`: the date itself, or the end of its period.
+    fn slot(&self, date: NaiveDate) -> NaiveDate {
+        match *self {
+            Frequency::Daily | Frequency::BusinessDaily | Frequency::BusinessDays(_) => date,
+            Frequency::Period(collapse) => period_end(date, collapse),
+        }
+    }
+
+    /// Whether `date` is an expected date.
+    fn expects(&self, date: NaiveDate) -> bool {
+        match *self {
+            Frequency::Daily => true,
+            Frequency::BusinessDaily => Calendar::new().is_business_day(date),
+            Frequency::BusinessDays(ref calendar) => calendar.is_business_day(date),
+            Frequency::Period(collapse) => period_end(date, collapse) == date,
+        }
+    }
+}
+
+/// A run of consecutive expected dates without a value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Gap {
+    /// First expected date without a value
+    pub start: NaiveDate,
+    /// Last expected date without a value
+    pub end: NaiveDate,
+    /// Number of expected dates without a value
+    pub periods: usize,
+}
+
+/// How `fill` replaces missing values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fill {
+    /// The last earlier value
+    Forward,
+    /// The next later value
+    Backward,
+    /// Interpolate linearly between the surrounding values, counting observations
+    Linear,
+    /// Interpolate linearly between the surrounding values, counting days
+    Time,
+}
+
+/// The expected dates of `frequency` from `start` to `end`, both included. With
+/// `Frequency::Period`, the ends of the periods overlapping the range.
+pub fn expected_dates(start: NaiveDate, end: NaiveDate, frequency: &Frequency) -> Vec<NaiveDate> {
+    let mut dates = Vec::new();
+    let mut date = start;
+    while date <= end {
+        let slot = frequency.slot(date);
+        if frequency.expects(slot) {
+            dates.push(slot);
+        }
+        date = slot + Duration::days(1);
+    }
+    dates
+}
+
+/// The gaps of a series: runs of expected dates between its first and last date that are
+/// missing from the series or have a missing value. With `Frequency::Period`, a period is missing
+/// when none of its dates has a value. Dates of the series that are not expected, like weekends
+/// of a business daily series, are ignored.
+pub fn gaps(series: &TimeSeries, frequency: &Frequency) -> Vec<Gap> {
+    let (start, end) = match (series.dates().first(), series.dates().last()) {
+        (Some(&start), Some(&end)) => (start, end),
+        _ => return Vec::new(),
+    };
+    let observed: BTreeSet<NaiveDate> = series.iter()
+                                              .filter(|&(_, value)| value.is_some())
+                                              .map(|(date, _)| frequency.slot(date))
+                                              .collect();
+    let mut gaps: Vec<Gap> = Vec::new();
+    let mut previous_missing = false;
+    for date in expected_dates(start, end, frequency) {
+        if observed.contains(&date) {
+            previous_missing = false;
+            continue;
+        }
+        if previous_missing {
+            let gap = gaps.last_mut().unwrap();
+            gap.end = date;
+            gap.periods += 1;
+        } else {
+            gaps.push(Gap {
+                start: date,
+                end: date,
+                periods: 1,
+            });
+        }
+        previous_missing = true;
+    }
+    gaps
+}
+
+/// The series with a missing value on each of the expected dates between its first and last
+/// date that it doesn't have, so that `fill` can fill them. With `Frequency::Period`, a period
+/// without any date in the series gets a missing value on its last day.
+pub fn regularize(series: &TimeSeries, frequency: &Frequency) -> TimeSeries {
+    let (start, end) = match (series.dates().first(), series.dates().last()) {
+        (Some(&start), Some(&end)) => (start, end),
+        _ => return series.clone(),
+    };
+    let present: BTreeSet<NaiveDate> = series.dates()
+                                             .iter()
+                                             .map(|&date| frequency.slot(date))
+                                             .collect();
+    let missing = expected_dates(start, end, frequency)
+                      .into_iter()
+                      .filter(|date| !present.contains(date))
+                      .map(|date| (date, None));
+    TimeSeries::new(series.iter().chain(missing))
+}
+
+/// Replace the missing values of a series with `method`. At most `limit` consecutive missing
+/// values of each run are filled: the first ones, or the last ones with `Fill::Backward`.
+/// Interpolation only fills runs with a value on both sides. Dates that are not in the series
+/// are not added; use `regularize` first to fill the gaps of a frequency.
+pub fn fill(series: &TimeSeries, method: Fill, limit: Option<usize>) -> TimeSeries {
+    let dates = series.dates();
+    let mut values = series.values().to_vec();
+    let mut start = 0;
+    while start < values.len() {
+        if values[start].is_some() {
+            start += 1;
+            continue;
+        }
+        let mut end = start;
+        while end < values.len() && values[end].is_none() {
+            end += 1;
+        }
+        let before = if start > 0 {
+            values[start - 1].map(|value| (start - 1, value))
+        } else {
+            None
+        };
+        let after = values.get(end).and_then(|value| value.map(|value| (end, value)));
+        let length = end - start;
+        let count = limit.map_or(length, |limit| ::std::cmp::min(limit, length));
+        let filled = if method == Fill::Backward {
+            end - count..end
+        } else {
+            start..start + count
+        };
+        for i in filled {
+            values[i] = match (method, before, after) {
+                (Fill::Forward, Some((_, value)), _) => Some(value),
+                (Fill::Backward, _, Some((_, value))) => Some(value),
+                (Fill::Linear, Some((a, from)), Some((b, to))) => {
+                    Some(interpolate(from, to, (i - a) as f64 / (b - a) as f64))
+                }
+                (Fill::Time, Some((a, from)), Some((b, to))) => {
+                    let elapsed = (dates[i] - dates[a]).num_days() as f64;
+                    let total = (dates[b] - dates[a]).num_days() as f64;
+                    Some(interpolate(from, to, elapsed / total))
+                }
+                _ => None,
+            };
+        }
+        start = end;
+    }
+    TimeSeries::new(dates.iter().cloned().zip(values))
+}
+
+fn interpolate(from: f64, to: f64, fraction: f64) -> f64 {
+    from + (to - from) * fraction
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{Calendar, NaiveDate, Collapse, TimeSeries};
+
+    fn ymd(m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd(2015, m, d)
+    }
+
+    /// Business days from Thursday 2015-01-01 to Friday 2015-01-16 with the 6th, 7th and 13th
+    /// missing and a null on the 9th.
+    fn series() -> TimeSeries {
+        TimeSeries::new(vec![(ymd(1, 1), Some(1.0)),
+                             (ymd(1, 2), Some(2.0)),
+                             (ymd(1, 3), Some(9.0)),
+                             (ymd(1, 5), Some(3.0)),
+                             (ymd(1, 8), Some(6.0)),
+                             (ymd(1, 9), None),
+                             (ymd(1, 12), Some(8.0)),
+                             (ymd(1, 14), Some(10.0)),
+                             (ymd(1, 15), Some(11.0)),
+                             (ymd(1, 16), Some(12.0))])
+    }
+
+    #[test]
+    fn test_expected_dates() {
+        assert_eq!(expected_dates(ymd(1, 2), ymd(1, 6), &Frequency::BusinessDaily),
+                   vec![ymd(1, 2), ymd(1, 5), ymd(1, 6)]);
+        assert_eq!(expected_dates(ymd(1, 30), ymd(2, 1), &Frequency::Daily).len(), 3);
+        assert_eq!(expected_dates(ymd(1, 15), ymd(3, 1), &Frequency::Period(Collapse::Monthly)),
+                   vec![ymd(1, 31), ymd(2, 28), ymd(3, 31)]);
+        assert!(expected_dates(ymd(1, 3), ymd(1, 4), &Frequency::BusinessDaily).is_empty());
+        assert_eq!(expected_dates(ymd(1, 1), ymd(2, 1), &Frequency::BusinessDaily),
+                   expected_dates(ymd(1, 1), ymd(2, 1), &Frequency::BusinessDays(Calendar::new())));
+    }
+
+    #[test]
+    fn test_gaps() {
+        let series = series();
+        assert_eq!(gaps(&series, &Frequency::BusinessDaily),
+                   vec![Gap {
+                            start: ymd(1, 6),
+                            end: ymd(1, 7),
+                            periods: 2,
+                        },
+                        Gap {
+                            start: ymd(1, 9),
+                            end: ymd(1, 9),
+                            periods: 1,
+                        },
+                        Gap {
+                            start: ymd(1, 13),
+                            end: ymd(1, 13),
+                            periods: 1,
+                        }]);
+        assert_eq!(gaps(&series, &Frequency::Daily)[0],
+                   Gap {
+                       start: ymd(1, 4),
+                       end: ymd(1, 4),
+                       periods: 1,
+                   });
+        assert!(gaps(&series, &Frequency::Period(Collapse::Weekly)).is_empty());
+        assert!(gaps(&TimeSeries::default(), &Frequency::Daily).is_empty());
+
+        // Good Friday is a holiday of the NYSE
+        let easter = TimeSeries::new(vec![(ymd(4, 2), Some(1.0)), (ymd(4, 7), Some(2.0))]);
+        assert_eq!(gaps(&easter, &Frequency::BusinessDaily)[0].periods, 2);
+        assert_eq!(gaps(&easter, &Frequency::BusinessDays(Calendar::nyse())),
+                   vec![Gap {
+                            start: ymd(4, 6),
+                            end: ymd(4, 6),
+                            periods: 1,
+                        }]);
+        assert!(gaps(&easter, &Frequency::BusinessDays(Calendar::lse())).is_empty());
+    }
+
+    #[test]
+    fn test_regularize() {
+        let series = regularize(&series(), &Frequency::BusinessDaily);
+        assert_eq!(series.len(), 13);
+        assert_eq!(series.get(ymd(1, 3)), Some(9.0));
+        assert!(series.dates().contains(&ymd(1, 7)));
+        assert!(!series.dates().contains(&ymd(1, 10)));
+
+        let monthly = TimeSeries::new(vec![(ymd(1, 5), Some(1.0)), (ymd(3, 2), Some(2.0))]);
+        assert_eq!(regularize(&monthly, &Frequency::Period(Collapse::Monthly)).dates(),
+                   &[ymd(1, 5), ymd(2, 28), ymd(3, 2)]);
+    }
+
+    #[test]
+    fn test_fill() {
+        let series = TimeSeries::new(vec![(ymd(1, 1), None),
+                                          (ymd(1, 2), Some(1.0)),
+                                          (ymd(1, 3), None),
+                                          (ymd(1, 4), None),
+                                          (ymd(1, 8), None),
+                                          (ymd(1, 10), Some(10.0)),
+                                          (ymd(1, 11), None)]);
+        assert_eq!(fill(&series, Fill::Forward, None), series.ffill());
+        assert_eq!(fill(&series, Fill::Backward, None), series.bfill());
+        assert_eq!(fill(&series, Fill::Forward, Some(2)).values(),
+                   &[None, Some(1.0), Some(1.0), Some(1.0), None, Some(10.0), Some(10.0)]);
+        assert_eq!(fill(&series, Fill::Backward, Some(1)).values(),
+                   &[Some(1.0), Some(1.0), None, None, Some(10.0), Some(10.0), None]);
+        assert_eq!(fill(&series, Fill::Linear, None).values(),
+                   &[None, Some(1.0), Some(3.25), Some(5.5), Some(7.75), Some(10.0), None]);
+        assert_eq!(fill(&series, Fill::Time, Some(2)).values(),
+                   &[None, Some(1.0), Some(2.125), Some(3.25), None, Some(10.0), None]);
+    }
+}
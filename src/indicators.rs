@@ -0,0 +1,484 @@
+use std::collections::VecDeque;
+use super::{Bar, TimeSeries};
+
+/// A technical indicator updated one observation at a time. `update` returns `None` until
+/// enough observations have been seen. Use `batch` to compute an indicator over a whole series.
+pub trait Indicator<T> {
+    /// Value produced for each observation
+    type Output;
+
+    /// Add the next observation, in ascending date order.
+    fn update(&mut self, input: T) -> Option<Self::Output>;
+}
+
+/// Compute `indicator` over all `inputs`, in ascending date order.
+pub fn batch<T, I, It>(mut indicator: I, inputs: It) -> Vec<Option<I::Output>>
+    where I: Indicator<T>,
+          It: IntoIterator<Item = T>
+{
+    inputs.into_iter().map(|input| indicator.update(input)).collect()
+}
+
+/// Compute `indicator` over the values of `series`. Missing values are skipped and stay missing.
+pub fn on_series<I>(mut indicator: I, series: &TimeSeries) -> TimeSeries
+    where I: Indicator<f64, Output = f64>
+{
+    TimeSeries::new(series.iter()
+                          .map(|(date, value)| {
+                              (date, value.and_then(|value| indicator.update(value)))
+                          }))
+}
+
+/// Simple moving average of the last `period` values.
+#[derive(Debug, Clone)]
+pub struct Sma {
+    period: usize,
+    window: VecDeque<f64>,
+}
+
+impl Sma {
+    /// Creates a simple moving average. Panics if `period` is zero.
+    pub fn new(period: usize) -> Sma {
+        assert!(period > 0, "period must be greater than zero");
+        Sma {
+            period: period,
+            window: VecDeque::with_capacity(period + 1),
+        }
+    }
+}
+
+impl Indicator<f64> for Sma {
+    type Output = f64;
+
+    fn update(&mut self, value: f64) -> Option<f64> {
+        self.window.push_back(value);
+        if self.window.len() > self.period {
+            self.window.pop_front();
+        }
+        if self.window.len() == self.period {
+            // sum the window each time, a running sum accumulates rounding errors
+            Some(self.window.iter().fold(0.0, |sum, v| sum + v) / self.period as f64)
+        } else {
+            None
+        }
+    }
+}
+
+/// Exponential moving average with smoothing `2 / (period + 1)`, seeded with the simple
+/// average of the first `period` values.
+#[derive(Debug, Clone)]
+pub struct Ema {
+    period: usize,
+    alpha: f64,
+    seed: Vec<f64>,
+    value: Option<f64>,
+}
+
+impl Ema {
+    /// Creates an exponential moving average. Panics if `period` is zero.
+    pub fn new(period: usize) -> Ema {
+        assert!(period > 0, "period must be greater than zero");
+        Ema {
+            period: period,
+            alpha: 2.0 / (period as f64 + 1.0),
+            seed: Vec::with_capacity(period),
+            value: None,
+        }
+    }
+}
+
+impl Indicator<f64> for Ema {
+    type Output = f64;
+
+    fn update(&mut self, value: f64) -> Option<f64> {
+        self.value = match self.value {
+            Some(previous) => Some(self.alpha * value + (1.0 - self.alpha) * previous),
+            None => {
+                self.seed.push(value);
+                if self.seed.len() == self.period {
+                    Some(self.seed.iter().fold(0.0, |sum, v| sum + v) / self.period as f64)
+                } else {
+                    None
+                }
+            }
+        };
+        self.value
+    }
+}
+
+/// Relative strength index with Wilder's smoothing. The first value is produced after
+/// `period` changes, i.e. `period + 1` prices.
+#[derive(Debug, Clone)]
+pub struct Rsi {
+    period: usize,
+    previous: Option<f64>,
+    changes: usize,
+    gain: f64,
+    loss: f64,
+}
+
+impl Rsi {
+    /// Creates a relative strength index. Panics if `period` is zero.
+    pub fn new(period: usize) -> Rsi {
+        assert!(period > 0, "period must be greater than zero");
+        Rsi {
+            period: period,
+            previous: None,
+            changes: 0,
+            gain: 0.0,
+            loss: 0.0,
+        }
+    }
+}
+
+impl Indicator<f64> for Rsi {
+    type Output = f64;
+
+    fn update(&mut self, value: f64) -> Option<f64> {
+        let previous = self.previous;
+        self.previous = Some(value);
+        let change = match previous {
+            Some(previous) => value - previous,
+            None => return None,
+        };
+        let (gain, loss) = (change.max(0.0), (-change).max(0.0));
+        let period = self.period as f64;
+        self.changes += 1;
+        if self.changes <= self.period {
+            // simple average of the first changes
+            self.gain += gain / period;
+            self.loss += loss / period;
+            if self.changes < self.period {
+                return None;
+            }
+        } else {
+            self.gain = (self.gain * (period - 1.0) + gain) / period;
+            self.loss = (self.loss * (period - 1.0) + loss) / period;
+        }
+        if self.loss == 0.0 {
+            Some(100.0)
+        } else {
+            Some(100.0 - 100.0 / (1.0 + self.gain / self.loss))
+        }
+    }
+}
+
+/// A value of `Macd`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MacdValue {
+    /// Fast EMA minus slow EMA
+    pub macd: f64,
+    /// EMA of the MACD line, `None` until enough MACD values have been seen
+    pub signal: Option<f64>,
+    /// MACD minus signal
+    pub histogram: Option<f64>,
+}
+
+/// Moving average convergence divergence, usually with periods 12, 26 and 9.
+#[derive(Debug, Clone)]
+pub struct Macd {
+    fast: Ema,
+    slow: Ema,
+    signal: Ema,
+}
+
+impl Macd {
+    /// Creates a MACD with the given fast, slow and signal periods. Panics if a period is zero.
+    pub fn new(fast: usize, slow: usize, signal: usize) -> Macd {
+        Macd {
+            fast: Ema::new(fast),
+            slow: Ema::new(slow),
+            signal: Ema::new(signal),
+        }
+    }
+}
+
+impl Indicator<f64> for Macd {
+    type Output = MacdValue;
+
+    fn update(&mut self, value: f64) -> Option<MacdValue> {
+        let fast = self.fast.update(value);
+        let slow = self.slow.update(value);
+        match (fast, slow) {
+            (Some(fast), Some(slow)) => {
+                let macd = fast - slow;
+                let signal = self.signal.update(macd);
+                Some(MacdValue {
+                    macd: macd,
+                    signal: signal,
+                    histogram: signal.map(|signal| macd - signal),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A value of `Bollinger`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bands {
+    /// Middle band minus `k` standard deviations
+    pub lower: f64,
+    /// Simple moving average
+    pub middle: f64,
+    /// Middle band plus `k` standard deviations
+    pub upper: f64,
+}
+
+/// Bollinger bands: the simple moving average of the last `period` values and `k` population
+/// standard deviations around it, usually with a period of 20 and `k` of 2.
+#[derive(Debug, Clone)]
+pub struct Bollinger {
+    period: usize,
+    k: f64,
+    window: VecDeque<f64>,
+}
+
+impl Bollinger {
+    /// Creates Bollinger bands. Panics if `period` is zero.
+    pub fn new(period: usize, k: f64) -> Bollinger {
+        assert!(period > 0, "period must be greater than zero");
+        Bollinger {
+            period: period,
+            k: k,
+            window: VecDeque::with_capacity(period + 1),
+        }
+    }
+}
+
+impl Indicator<f64> for Bollinger {
+    type Output = Bands;
+
+    fn update(&mut self, value: f64) -> Option<Bands> {
+        self.window.push_back(value);
+        if self.window.len() > self.period {
+            self.window.pop_front();
+        }
+        if self.window.len() < self.period {
+            return None;
+        }
+        let n = self.period as f64;
+        let mean = self.window.iter().fold(0.0, |sum, v| sum + v) / n;
+        let variance = self.window.iter().fold(0.0, |sum, v| sum + (v - mean) * (v - mean)) / n;
+        let width = self.k * variance.sqrt();
+        Some(Bands {
+            lower: mean - width,
+            middle: mean,
+            upper: mean + width,
+        })
+    }
+}
+
+/// Average true range with Wilder's smoothing. The true range of the first bar is its high
+/// minus its low.
+#[derive(Debug, Clone)]
+pub struct Atr {
+    period: usize,
+    previous_close: Option<f64>,
+    ranges: usize,
+    value: f64,
+}
+
+impl Atr {
+    /// Creates an average true range. Panics if `period` is zero.
+    pub fn new(period: usize) -> Atr {
+        assert!(period > 0, "period must be greater than zero");
+        Atr {
+            period: period,
+            previous_close: None,
+            ranges: 0,
+            value: 0.0,
+        }
+    }
+}
+
+impl<'a> Indicator<&'a Bar> for Atr {
+    type Output = f64;
+
+    fn update(&mut self, bar: &'a Bar) -> Option<f64> {
+        let range = match self.previous_close {
+            Some(close) => {
+                (bar.high - bar.low).max((bar.high - close).abs()).max((bar.low - close).abs())
+            }
+            None => bar.high - bar.low,
+        };
+        self.previous_close = Some(bar.close);
+        let period = self.period as f64;
+        self.ranges += 1;
+        if self.ranges <= self.period {
+            self.value += range / period;
+            if self.ranges < self.period {
+                return None;
+            }
+        } else {
+            self.value = (self.value * (period - 1.0) + range) / period;
+        }
+        Some(self.value)
+    }
+}
+
+/// On-balance volume: the running sum of the volume of up days minus the volume of down days,
+/// starting at zero.
+#[derive(Debug, Clone, Default)]
+pub struct Obv {
+    previous_close: Option<f64>,
+    value: f64,
+}
+
+impl Obv {
+    /// Creates an on-balance volume.
+    pub fn new() -> Obv {
+        Obv::default()
+    }
+}
+
+impl<'a> Indicator<&'a Bar> for Obv {
+    type Output = f64;
+
+    fn update(&mut self, bar: &'a Bar) -> Option<f64> {
+        if let Some(close) = self.previous_close {
+            if bar.close > close {
+                self.value += bar.volume;
+            } else if bar.close < close {
+                self.value -= bar.volume;
+            }
+        }
+        self.previous_close = Some(bar.close);
+        Some(self.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use super::*;
+    use super::super::{Bar, JsonValue, NaiveDate, TimeSeries};
+
+    fn fixture() -> JsonValue {
+        serde_json::from_str(include_str!("../tests/fixtures/indicators.json")).unwrap()
+    }
+
+    fn bars(fixture: &JsonValue) -> Vec<Bar> {
+        fixture.lookup("bars")
+               .and_then(|bars| bars.as_array())
+               .unwrap()
+               .iter()
+               .map(|bar| {
+                   let field = |name: &str| bar.lookup(name).and_then(|v| v.as_f64()).unwrap();
+                   let date = bar.lookup("date").and_then(|date| date.as_string()).unwrap();
+                   let close = field("close");
+                   Bar {
+                       date: date.parse().unwrap(),
+                       open: close,
+                       high: field("high"),
+                       low: field("low"),
+                       close: close,
+                       volume: field("volume"),
+                       ex_dividend: None,
+                       split_ratio: None,
+                       adj_open: None,
+                       adj_high: None,
+                       adj_low: None,
+                       adj_close: None,
+                       adj_volume: None,
+                   }
+               })
+               .collect()
+    }
+
+    fn expected(fixture: &JsonValue, path: &str) -> Vec<Option<f64>> {
+        fixture.lookup(path)
+               .and_then(|values| values.as_array())
+               .unwrap()
+               .iter()
+               .map(|value| value.as_f64())
+               .collect()
+    }
+
+    fn assert_values(actual: &[Option<f64>], expected: &[Option<f64>], name: &str) {
+        assert_eq!(actual.len(), expected.len());
+        for (i, (a, e)) in actual.iter().zip(expected.iter()).enumerate() {
+            match (*a, *e) {
+                (Some(a), Some(e)) => {
+                    assert!((a - e).abs() < 1e-9, "{}[{}]: {} != {}", name, i, a, e)
+                }
+                (a, e) => assert!(a == e, "{}[{}]: {:?} != {:?}", name, i, a, e),
+            }
+        }
+    }
+
+    #[test]
+    fn test_reference_values() {
+        let fixture = fixture();
+        let bars = bars(&fixture);
+        let closes: Vec<f64> = bars.iter().map(|bar| bar.close).collect();
+
+        assert_values(&batch(Sma::new(5), closes.iter().cloned()),
+                      &expected(&fixture, "sma_5"),
+                      "sma");
+        assert_values(&batch(Ema::new(5), closes.iter().cloned()),
+                      &expected(&fixture, "ema_5"),
+                      "ema");
+        assert_values(&batch(Rsi::new(14), closes.iter().cloned()),
+                      &expected(&fixture, "rsi_14"),
+                      "rsi");
+
+        let macd = batch(Macd::new(12, 26, 9), closes.iter().cloned());
+        assert_values(&macd.iter().map(|v| v.map(|v| v.macd)).collect::<Vec<_>>(),
+                      &expected(&fixture, "macd_12_26_9.macd"),
+                      "macd");
+        assert_values(&macd.iter().map(|v| v.and_then(|v| v.signal)).collect::<Vec<_>>(),
+                      &expected(&fixture, "macd_12_26_9.signal"),
+                      "signal");
+        assert_values(&macd.iter().map(|v| v.and_then(|v| v.histogram)).collect::<Vec<_>>(),
+                      &expected(&fixture, "macd_12_26_9.histogram"),
+                      "histogram");
+
+        let bands = batch(Bollinger::new(20, 2.0), closes.iter().cloned());
+        assert_values(&bands.iter().map(|v| v.map(|v| v.lower)).collect::<Vec<_>>(),
+                      &expected(&fixture, "bollinger_20_2.lower"),
+                      "lower");
+        assert_values(&bands.iter().map(|v| v.map(|v| v.middle)).collect::<Vec<_>>(),
+                      &expected(&fixture, "bollinger_20_2.middle"),
+                      "middle");
+        assert_values(&bands.iter().map(|v| v.map(|v| v.upper)).collect::<Vec<_>>(),
+                      &expected(&fixture, "bollinger_20_2.upper"),
+                      "upper");
+
+        assert_values(&batch(Atr::new(14), bars.iter()),
+                      &expected(&fixture, "atr_14"),
+                      "atr");
+        assert_values(&batch(Obv::new(), bars.iter()), &expected(&fixture, "obv"), "obv");
+    }
+
+    #[test]
+    fn test_streaming_matches_batch() {
+        let fixture = fixture();
+        let closes: Vec<f64> = bars(&fixture).iter().map(|bar| bar.close).collect();
+        let mut ema = Ema::new(10);
+        let streamed: Vec<Option<f64>> = closes.iter().map(|&close| ema.update(close)).collect();
+        assert_eq!(streamed, batch(Ema::new(10), closes.iter().cloned()));
+        // the tenth value is the simple average of the first ten
+        assert_eq!(streamed[8], None);
+        assert_values(&streamed[9..10],
+                      &batch(Sma::new(10), closes.iter().cloned())[9..10],
+                      "seed");
+    }
+
+    #[test]
+    fn test_on_series() {
+        let date = |d| NaiveDate::from_ymd(2015, 1, d);
+        let series = TimeSeries::new(vec![(date(1), Some(1.0)),
+                                          (date(2), None),
+                                          (date(3), Some(3.0)),
+                                          (date(4), Some(5.0))]);
+        assert_eq!(on_series(Sma::new(2), &series).values(),
+                   &[None, None, Some(2.0), Some(4.0)]);
+    }
+
+    #[test]
+    fn test_rsi_without_losses() {
+        assert_eq!(batch(Rsi::new(2), vec![1.0, 2.0, 3.0, 4.0]),
+                   vec![None, None, Some(100.0), Some(100.0)]);
+    }
+}
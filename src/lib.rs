@@ -17,15 +17,40 @@
 //! for financial and economic datasets.
 
 extern crate curl;
+extern crate serde;
 extern crate serde_json;
 extern crate url;
 extern crate hyper;
 #[macro_use] extern crate quick_error;
 extern crate chrono;
+extern crate crossbeam;
+#[cfg(feature = "arrow")]
+extern crate arrow;
+#[cfg(feature = "polars")]
+extern crate polars;
+#[cfg(feature = "export-parquet")]
+extern crate parquet;
+#[cfg(feature = "derive")]
+extern crate quandl_derive;
+#[cfg(test)]
+extern crate toml;
 
 pub use quandl::Quandl;
 pub use quandl_request::*;
-pub use error::{Error, Result};
+pub use owned_request::OwnedQuandlRequest;
+pub use spec::RequestSpec;
+pub use code::QuandlCode;
+pub use date::{Clock, SystemClock, FixedClock, RelativeDate, DateBound, DateRange};
+pub use chunk::{Chunking, ChunkWindow};
+pub use row::{Row, Dataset, RowIter, JsonRowIter};
+pub use export::CsvOptions;
+pub use typed::{QuandlRow, RowLayout, FromValue};
+pub use bar::Bar;
+pub use series::TimeSeries;
+pub use calendar::{Calendar, BusinessDay};
+#[cfg(feature = "derive")]
+pub use quandl_derive::QuandlRow;
+pub use error::{Error, Result, ValidationError};
 pub use serde_json::Value as JsonValue;
 pub use chrono::NaiveDate as NaiveDate;
 
@@ -33,5 +58,46 @@ pub use chrono::NaiveDate as NaiveDate;
 pub mod quandl;
 /// Handles building and sending requests to Quandl
 pub mod quandl_request;
+/// Owned requests sharing a `Quandl` client across threads
+pub mod owned_request;
+/// Serializable request parameters
+pub mod spec;
+/// Parsing of Quandl codes like `WIKI/AAPL.4`
+pub mod code;
+/// Relative dates, date ranges and clocks
+pub mod date;
+/// Splitting long date ranges into several requests
+pub mod chunk;
+/// Typed rows and datasets, and streaming of CSV responses
+pub mod row;
+/// Positional decoding of rows into user types
+pub mod typed;
+/// Daily price bars of equity price databases
+pub mod bar;
+/// Split and dividend adjustment of price bars
+pub mod adjust;
+/// Continuous futures series built from individual contracts
+pub mod futures;
+/// Date indexed series with alignment and rolling windows
+pub mod series;
+/// Technical indicators with streaming and batch computation
+pub mod indicators;
+/// Risk and performance statistics of return series
+pub mod stats;
+/// Business-day and exchange holiday calendars
+pub mod calendar;
+/// Gap detection and filling of missing values
+pub mod gaps;
+/// Local implementation of Quandl's transforms
+pub mod transform;
+/// Local resampling matching Quandl's collapse
+pub mod resample;
+/// Conversion of datasets into Arrow record batches and Polars data frames
+#[cfg(any(feature = "arrow", feature = "polars"))]
+pub mod frame;
+/// Writing datasets to CSV, JSON Lines and Parquet
+pub mod export;
 /// Errors
 pub mod error;
+mod column;
+mod serde_util;
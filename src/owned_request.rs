@@ -0,0 +1,84 @@
+use std::sync::Arc;
+use error::Result;
+use super::{JsonValue, Quandl, QuandlRequest, RequestSpec};
+
+/// Owned counterpart of `QuandlRequest`. Instead of borrowing the `Quandl` client it holds a
+/// shared `Arc` handle, which makes it `Send + 'static` so it can be moved into worker threads,
+/// stored in job queues and cloned to be rescheduled. The request parameters are kept in a
+/// serializable `RequestSpec`.
+#[derive(Debug, Clone)]
+pub struct OwnedQuandlRequest {
+    /// Shared handle to the Quandl client used to send the request.
+    pub quandl: Arc<Quandl>,
+    /// Parameters of the request.
+    pub spec: RequestSpec,
+}
+
+impl OwnedQuandlRequest {
+    /// Creates a new `OwnedQuandlRequest` using the specified database_code and dataset code.
+    /// All optional parameters are set to `None`.
+    pub fn new(quandl: Arc<Quandl>,
+               database_code: &str,
+               dataset_code: &str)
+               -> OwnedQuandlRequest {
+        OwnedQuandlRequest::from_spec(quandl, RequestSpec::new(database_code, dataset_code))
+    }
+
+    /// Creates a new `OwnedQuandlRequest` from previously stored parameters.
+    pub fn from_spec(quandl: Arc<Quandl>, spec: RequestSpec) -> OwnedQuandlRequest {
+        OwnedQuandlRequest {
+            quandl: quandl,
+            spec: spec,
+        }
+    }
+
+    /// Borrow this request as a `QuandlRequest`, e.g. to keep using the builder methods. Use
+    /// `QuandlRequest::into_owned` to get an `OwnedQuandlRequest` back.
+    pub fn as_request(&self) -> QuandlRequest {
+        self.spec.to_request(&self.quandl)
+    }
+
+    /// Make a request to the Quandl API with the specified parameters
+    pub fn run(&self) -> Result<JsonValue> {
+        self.as_request().run()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+    use super::*;
+    use super::super::{Quandl, Order};
+
+    fn assert_send_static<T: Send + 'static>() {}
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_thread_safety() {
+        assert_send_sync::<Quandl>();
+        assert_send_static::<OwnedQuandlRequest>();
+    }
+
+    #[test]
+    fn test_into_owned() {
+        let q = Arc::new(Quandl::new().api_key("key"));
+        let req = q.new_request("WIKI", "AAPL").limit(5).order(Order::Asc).into_owned(q.clone());
+        assert_eq!(req.spec.database_code, String::from("WIKI"));
+        assert_eq!(req.spec.dataset_code, String::from("AAPL"));
+        assert_eq!(req.spec.limit, Some(5));
+        assert_eq!(req.spec.order, Some(Order::Asc));
+        assert_eq!(req.quandl.api_key, Some(String::from("key")));
+    }
+
+    #[test]
+    fn test_move_to_thread() {
+        let q = Arc::new(Quandl::new());
+        let req = OwnedQuandlRequest::new(q.clone(), "WIKI", "AAPL");
+        let handle = thread::spawn(move || {
+            let req = req.as_request().rows(1).into_owned(req.quandl.clone());
+            req.spec.rows
+        });
+        assert_eq!(handle.join().unwrap(), Some(1));
+    }
+}
@@ -1,13 +1,25 @@
+use std::collections::HashMap;
 use std::fmt::{self, Formatter, Debug};
+use std::sync::{Arc, Mutex};
 use hyper;
-use super::QuandlRequest;
+use serde_json;
+use error::{Error, Result};
+use url::Url;
+use quandl_request::QUANDL_BASE_URL;
+use bar;
+use super::{QuandlRequest, QuandlCode, RequestSpec, JsonValue, Clock, SystemClock, Bar};
 
-/// Parameters for Quandl
+/// Parameters for Quandl. `Quandl` is `Send + Sync`, so it can be shared across threads
+/// with an `Arc` (see `OwnedQuandlRequest`).
 pub struct Quandl {
     /// Http client
     pub http_client: hyper::Client,
     /// Quandl API key. Used for premium databases and/or increased usage limits
     pub api_key: Option<String>,
+    /// Clock used to resolve relative dates such as `30d` or `ytd`.
+    pub clock: Arc<Clock>,
+    /// Column names of datasets, keyed by database and dataset code.
+    column_names: Mutex<HashMap<(String, String), Vec<String>>>,
 }
 
 impl Quandl {
@@ -27,11 +39,92 @@ impl Quandl {
         }
     }
 
+    /// Creates a new `QuandlRequest` from a Quandl code such as `WIKI/AAPL`. A column can be
+    /// selected with the `WIKI/AAPL.4` syntax, which sets `column_index`. Fails when the code
+    /// can't be parsed (see `QuandlCode`).
+    pub fn request_for(&self, code: &str) -> Result<QuandlRequest> {
+        let code = try!(code.parse::<QuandlCode>());
+        Ok(QuandlRequest {
+            column_index: code.column_index,
+            ..self.new_request(&code.database_code, &code.dataset_code)
+        })
+    }
+
+    /// Column names of a dataset, starting with the date column. The names are fetched from the
+    /// dataset metadata the first time and cached on the client afterwards.
+    pub fn column_names(&self, database_code: &str, dataset_code: &str) -> Result<Vec<String>> {
+        let key = (String::from(database_code), String::from(dataset_code));
+        if let Some(names) = self.column_names.lock().unwrap().get(&key) {
+            return Ok(names.clone());
+        }
+
+        let url = format!("{}/{}/{}/metadata.json", QUANDL_BASE_URL, database_code, dataset_code);
+        let mut url = try!(Url::parse(&url));
+        if let Some(ref api_key) = self.api_key {
+            url.set_query_from_pairs(vec![("api_key", api_key.clone())]);
+        }
+        let res = try!(self.http_client.get(url).send());
+        let status = res.status;
+        let data: JsonValue = try!(serde_json::from_reader(res));
+        if status != hyper::Ok {
+            return Err(Error::Quandl(format!("quandl metadata request failed with code `{}` and \
+                                              response: {:?}",
+                                             status,
+                                             data)));
+        }
+
+        let names = match data.lookup("dataset.column_names").and_then(|names| names.as_array()) {
+            Some(names) => {
+                names.iter().filter_map(|name| name.as_string().map(String::from)).collect()
+            }
+            None => {
+                return Err(Error::Parse(format!("unexpected metadata response: {:?}", data)))
+            }
+        };
+        self.cache_column_names(database_code, dataset_code, names.clone());
+        Ok(names)
+    }
+
+    /// Store the column names of a dataset in the cache used by `column_names`, e.g. to preload
+    /// them from a local copy of the metadata.
+    pub fn cache_column_names(&self, database_code: &str, dataset_code: &str, names: Vec<String>) {
+        let key = (String::from(database_code), String::from(dataset_code));
+        self.column_names.lock().unwrap().insert(key, names);
+    }
+
+    /// Executes a previously built or deserialized `RequestSpec`, sending exactly the stored
+    /// parameters to the Quandl API.
+    pub fn execute(&self, spec: &RequestSpec) -> Result<JsonValue> {
+        spec.to_request(self).run()
+    }
+
+    /// Fetches the daily price bars of an equity dataset such as `WIKI/AAPL` or `EOD/MSFT`. The
+    /// column layouts of `WIKI`, `EOD` and similar databases are recognized, see `Bar`. Days with
+    /// a missing price or volume are skipped, see `bar::decode_bars`. Codes selecting a single
+    /// column, like `WIKI/AAPL.4`, are rejected since a bar needs all the price columns.
+    pub fn bars(&self, code: &str) -> Result<Vec<Bar>> {
+        let request = try!(self.request_for(code));
+        if let Some(index) = request.column_index {
+            return Err(Error::Column(format!("`{}` selects column {}, but bars need all the \
+                                              price columns",
+                                             code,
+                                             index)));
+        }
+        bar::decode_bars(&try!(request.run_dataset()))
+    }
+
     /// Quandl API key. Used for premium databases and/or increased usage limits.
     pub fn api_key(mut self, key: &str) -> Quandl {
         self.api_key = Some(String::from(key));
         self
     }
+
+    /// Clock used to resolve relative dates. Defaults to `SystemClock`; use a `FixedClock` to
+    /// get deterministic dates in tests.
+    pub fn clock<C: Clock + 'static>(mut self, clock: C) -> Quandl {
+        self.clock = Arc::new(clock);
+        self
+    }
 }
 
 impl Default for Quandl {
@@ -39,6 +132,8 @@ impl Default for Quandl {
         Quandl {
             http_client: hyper::Client::new(),
             api_key: None,
+            clock: Arc::new(SystemClock),
+            column_names: Mutex::new(HashMap::new()),
         }
     }
 }
@@ -47,6 +142,7 @@ impl Debug for Quandl {
     fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
         fmt.debug_struct("QuandlRequest")
            .field("api_key", &self.api_key)
+           .field("clock", &self.clock)
            .finish()
     }
 }
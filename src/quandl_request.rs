@@ -1,14 +1,29 @@
 use url::Url;
 use std::fmt::{self, Display, Formatter, Debug};
+use std::io::{self, BufReader, Read, Write};
+use std::str::FromStr;
 use hyper;
+use hyper::client::Response;
+use hyper::header::Headers;
+use hyper::status::StatusCode;
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
 use serde_json;
-use error::{Error, Result};
-use super::{NaiveDate, JsonValue, Quandl};
+use std::sync::Arc;
+use error::{Error, Result, ValidationError};
+use code::is_valid_code;
+use date::{DateBound, DateRange};
+use serde_util::FromStrVisitor;
+use typed::QuandlRow;
+use chunk;
+use column;
+use super::{NaiveDate, JsonValue, Quandl, OwnedQuandlRequest, RequestSpec, Chunking, Dataset,
+            RowIter, JsonRowIter};
 
 /// use v3 of Quandl API
-const QUANDL_BASE_URL: &'static str = "https://www.quandl.com/api/v3/datasets";
+pub const QUANDL_BASE_URL: &'static str = "https://www.quandl.com/api/v3/datasets";
 
 /// Parameters for the request to Quandl API
+#[derive(Clone)]
 pub struct QuandlRequest<'a> {
     /// Reference to Quandl struct. This information will be available for all
     /// Quandl requests.
@@ -25,6 +40,8 @@ pub struct QuandlRequest<'a> {
     pub rows: Option<u64>,
     /// Request specific column.
     pub column_index: Option<u64>,
+    /// Request columns by name, resolved to column indexes using the dataset metadata.
+    pub columns: Vec<String>,
     /// Retrieve data within a specific date range, by setting start dates for your query.
     /// Set the start date with: start_date=yyyy-mm-dd
     pub start_date: Option<NaiveDate>,
@@ -37,6 +54,8 @@ pub struct QuandlRequest<'a> {
     pub collapse: Option<Collapse>,
     /// Perform calculations on your data prior to downloading.
     pub transform: Option<Transform>,
+    /// Split the date range into windows that are fetched separately.
+    pub chunking: Option<Chunking>,
 }
 
 impl<'a> Debug for QuandlRequest<'a> {
@@ -48,17 +67,38 @@ impl<'a> Debug for QuandlRequest<'a> {
            .field("limit", &self.limit)
            .field("rows", &self.rows)
            .field("column_index", &self.column_index)
+           .field("columns", &self.columns)
            .field("start_date", &self.start_date)
            .field("end_date", &self.end_date)
            .field("order", &self.order)
            .field("collapse", &self.collapse)
            .field("transform", &self.transform)
+           .field("chunking", &self.chunking)
            .finish()
     }
 }
 
+/// All variants of `Order`, used to parse and list valid values.
+const ORDERS: &'static [Order] = &[Order::Asc, Order::Desc];
+
+/// All variants of `Collapse`, used to parse and list valid values.
+const COLLAPSES: &'static [Collapse] = &[Collapse::None,
+                                         Collapse::Daily,
+                                         Collapse::Weekly,
+                                         Collapse::Monthly,
+                                         Collapse::Quarterly,
+                                         Collapse::Annual];
+
+/// All variants of `Transform`, used to parse and list valid values.
+const TRANSFORMS: &'static [Transform] = &[Transform::None,
+                                           Transform::Diff,
+                                           Transform::Rdiff,
+                                           Transform::RdiffFrom,
+                                           Transform::Cumul,
+                                           Transform::Normalize];
+
 /// Sort in ascending or descending order.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Order {
     /// ascending order
     Asc,
@@ -79,9 +119,37 @@ impl Display for Order {
     }
 }
 
+/// Parses the URL spelling of `Order` (as produced by its `Display` implementation),
+/// ignoring case.
+impl FromStr for Order {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Order> {
+        parse_variant(s, "order", ORDERS)
+    }
+}
+
+impl Serialize for Order {
+    fn serialize<S>(&self, serializer: &mut S) -> ::std::result::Result<(), S::Error>
+        where S: Serializer
+    {
+        serializer.visit_str(&self.to_string())
+    }
+}
+
+impl Deserialize for Order {
+    fn deserialize<D>(deserializer: &mut D) -> ::std::result::Result<Order, D::Error>
+        where D: Deserializer
+    {
+        deserializer.visit(FromStrVisitor::new())
+    }
+}
+
 /// Parameters to indicate the desired frequency.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Collapse {
+    /// No collapse, keep the frequency of the dataset
+    None,
     /// Daily
     Daily,
     /// Weekly
@@ -101,6 +169,7 @@ impl Display for Collapse {
         write!(f,
                "{}",
                match *self {
+                   Collapse::None => "none",
                    Collapse::Daily => "daily",
                    Collapse::Weekly => "weekly",
                    Collapse::Monthly => "monthly",
@@ -110,15 +179,46 @@ impl Display for Collapse {
     }
 }
 
+/// Parses the URL spelling of `Collapse` (as produced by its `Display` implementation),
+/// ignoring case.
+impl FromStr for Collapse {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Collapse> {
+        parse_variant(s, "collapse", COLLAPSES)
+    }
+}
+
+impl Serialize for Collapse {
+    fn serialize<S>(&self, serializer: &mut S) -> ::std::result::Result<(), S::Error>
+        where S: Serializer
+    {
+        serializer.visit_str(&self.to_string())
+    }
+}
+
+impl Deserialize for Collapse {
+    fn deserialize<D>(deserializer: &mut D) -> ::std::result::Result<Collapse, D::Error>
+        where D: Deserializer
+    {
+        deserializer.visit(FromStrVisitor::new())
+    }
+}
+
 /// Perform calculations on your data prior to downloading.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Transform {
+    /// No transformation
+    None,
     /// Row on row change. A parameter that will transform the data to show the difference
     /// between days.
     Diff,
     /// Percentage change. A parameter that will transform the data to show the difference
     /// between days divided by the previous day.
     Rdiff,
+    /// Percentage change from the latest value. A parameter that will transform the data to show
+    /// the difference between the latest value and each day divided by that day.
+    RdiffFrom,
     /// Cummulative change. A parameter that will calculate the sum of all preceding data returned.
     Cumul,
     /// Normalize (set starting value at 100). A parameter that will normalize the data to the
@@ -134,14 +234,42 @@ impl Display for Transform {
         write!(f,
                "{}",
                match *self {
+                   Transform::None => "none",
                    Transform::Diff => "diff",
                    Transform::Rdiff => "rdiff",
+                   Transform::RdiffFrom => "rdiff_from",
                    Transform::Cumul => "cumul",
                    Transform::Normalize => "normalize",
                })
     }
 }
 
+/// Parses the URL spelling of `Transform` (as produced by its `Display` implementation),
+/// ignoring case.
+impl FromStr for Transform {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Transform> {
+        parse_variant(s, "transform", TRANSFORMS)
+    }
+}
+
+impl Serialize for Transform {
+    fn serialize<S>(&self, serializer: &mut S) -> ::std::result::Result<(), S::Error>
+        where S: Serializer
+    {
+        serializer.visit_str(&self.to_string())
+    }
+}
+
+impl Deserialize for Transform {
+    fn deserialize<D>(deserializer: &mut D) -> ::std::result::Result<Transform, D::Error>
+        where D: Deserializer
+    {
+        deserializer.visit(FromStrVisitor::new())
+    }
+}
+
 impl<'a> QuandlRequest<'a> {
     /// Creates a new `QuandlRequest` using the specified database_code and dataset code.
     /// All other parameters as taken from the default implementation, setting the optional
@@ -167,21 +295,44 @@ impl<'a> QuandlRequest<'a> {
         self
     }
 
+    /// Request a column by name instead of by index, e.g. `column("Adj. Close")`. The name is
+    /// resolved using the dataset metadata, which is fetched once and cached on the `Quandl`
+    /// client. Call it several times to select multiple columns: one request is sent per column
+    /// and the results are merged by date. Can't be combined with `column_index`.
+    pub fn column(mut self, name: &str) -> QuandlRequest<'a> {
+        self.columns.push(String::from(name));
+        self
+    }
+
 
     /// Retrieve data within a specific date range, by setting start date for your query.
-    /// Takes either a `&str` in the format of `yyyy-mm-dd` or a `chrono::NaiveDate` as input.
+    /// Takes a `&str` in the format of `yyyy-mm-dd` or a relative date like `30d`, `1y`, `ytd`
+    /// or `last 30 days`, a `chrono::NaiveDate`, a `chrono::DateTime` or a `RelativeDate` as
+    /// input. Relative dates are resolved with the clock of the `Quandl` client.
     pub fn start_date<T: ?Sized + DateInput>(mut self, date: &T) -> Result<QuandlRequest<'a>> {
         try!(date.set_start_date(&mut self));
         Ok(self)
     }
 
     /// Retrieve data within a specific date range, by setting end date for your query.
-    /// Takes either a `&str` in the format of `yyyy-mm-dd` or a `chrono::NaiveDate` as input.
+    /// Takes the same inputs as `start_date`.
     pub fn end_date<T: ?Sized + DateInput>(mut self, date: &T) -> Result<QuandlRequest<'a>> {
         try!(date.set_end_date(&mut self));
         Ok(self)
     }
 
+    /// Set both the start and end date from a `DateRange`, resolving relative dates with the
+    /// clock of the `Quandl` client. Open ends of the range leave the current date unchanged.
+    pub fn date_range(mut self, range: &DateRange) -> Result<QuandlRequest<'a>> {
+        let (start_date, end_date) = try!(range.resolve(&*self.quandl.clock));
+        let start_date = start_date.or(self.start_date);
+        let end_date = end_date.or(self.end_date);
+        try!(validate_date(&start_date, &end_date));
+        self.start_date = start_date;
+        self.end_date = end_date;
+        Ok(self)
+    }
+
     /// Select the sort order. The default sort order is `Desc`.
     pub fn order(mut self, order: Order) -> QuandlRequest<'a> {
         self.order = Some(order);
@@ -199,21 +350,116 @@ impl<'a> QuandlRequest<'a> {
     }
 
     /// Perform calculations on your data prior to downloading. The transformations currently
-    /// available are row-on-row change, percentage change, cumulative sum, and normalize
-    /// (set starting value at 100). Set the transform parameter with:
-    /// `transform=none|diff|rdiff|cumul|normalize`.
+    /// available are row-on-row change, percentage change, percentage change from the latest
+    /// value, cumulative sum, and normalize (set starting value at 100). Set the transform
+    /// parameter with: `transform=none|diff|rdiff|rdiff_from|cumul|normalize`.
     pub fn transform(mut self, transform: Transform) -> QuandlRequest<'a> {
         self.transform = Some(transform);
         self
     }
 
-    /// Build the URL to send to the Quandl API
-    fn get_url(&self) -> Url {
-        let mut url: Url = Url::parse(&format!("{}/{}/{}/data.json",
-                                               QUANDL_BASE_URL,
-                                               self.database_code,
-                                               self.dataset_code))
-                               .unwrap();
+    /// Split the `start_date`..`end_date` range into windows (e.g. one per year) that are
+    /// fetched separately, sequentially or in parallel, and stitched back together with
+    /// duplicate dates removed and rows in the requested `order`. Useful for very long ranges
+    /// that would otherwise time out. Requires both dates to be set and can't be combined with
+    /// `limit`, `rows` or `transform`.
+    pub fn chunking(mut self, chunking: Chunking) -> QuandlRequest<'a> {
+        self.chunking = Some(chunking);
+        self
+    }
+
+    /// Converts this request into an `OwnedQuandlRequest` holding the given shared `Quandl`
+    /// handle instead of a borrow, so it can be moved into another thread or stored in a queue.
+    /// The borrowed `quandl` of this request is replaced by `quandl`.
+    pub fn into_owned(self, quandl: Arc<Quandl>) -> OwnedQuandlRequest {
+        OwnedQuandlRequest {
+            quandl: quandl,
+            spec: self.spec(),
+        }
+    }
+
+    /// The parameters of this request as a serializable `RequestSpec`.
+    pub fn spec(&self) -> RequestSpec {
+        RequestSpec {
+            database_code: self.database_code.clone(),
+            dataset_code: self.dataset_code.clone(),
+            limit: self.limit,
+            rows: self.rows,
+            column_index: self.column_index,
+            columns: self.columns.clone(),
+            start_date: self.start_date,
+            end_date: self.end_date,
+            order: self.order,
+            collapse: self.collapse,
+            transform: self.transform,
+            chunking: self.chunking,
+        }
+    }
+
+    /// Check the request parameters before sending them to Quandl. All problems are reported
+    /// together in an `Error::Validation`: empty or invalid database and dataset codes, both
+    /// `limit` and `rows` set, `limit` or `rows` set to zero and `start_date` after `end_date`.
+    /// `run` validates the request before sending it.
+    pub fn validate(&self) -> Result<()> {
+        to_result(self.validation_errors())
+    }
+
+    /// The problems reported by `validate`.
+    fn validation_errors(&self) -> Vec<ValidationError> {
+        let mut errs = Vec::new();
+
+        if self.database_code.is_empty() {
+            errs.push(ValidationError::EmptyDatabaseCode);
+        } else if !is_valid_code(&self.database_code) {
+            errs.push(ValidationError::InvalidDatabaseCode(self.database_code.clone()));
+        }
+        if self.dataset_code.is_empty() {
+            errs.push(ValidationError::EmptyDatasetCode);
+        } else if !is_valid_code(&self.dataset_code) {
+            errs.push(ValidationError::InvalidDatasetCode(self.dataset_code.clone()));
+        }
+        if self.column_index.is_some() && !self.columns.is_empty() {
+            errs.push(ValidationError::ColumnIndexAndColumns);
+        }
+        if self.limit.is_some() && self.rows.is_some() {
+            errs.push(ValidationError::LimitAndRows);
+        }
+        if self.limit == Some(0) {
+            errs.push(ValidationError::ZeroLimit);
+        }
+        if self.rows == Some(0) {
+            errs.push(ValidationError::ZeroRows);
+        }
+        if let (Some(start_date), Some(end_date)) = (self.start_date, self.end_date) {
+            if start_date > end_date {
+                errs.push(ValidationError::StartAfterEnd(start_date, end_date));
+            }
+        }
+
+        if let Some(ref chunking) = self.chunking {
+            if self.start_date.is_none() || self.end_date.is_none() {
+                errs.push(ValidationError::ChunkingWithoutDateRange);
+            }
+            if self.limit.is_some() || self.rows.is_some() {
+                errs.push(ValidationError::ChunkingWithLimit);
+            }
+            if self.transform.map_or(false, |transform| transform != Transform::None) {
+                errs.push(ValidationError::ChunkingWithTransform);
+            }
+            if chunking.window.size() == 0 {
+                errs.push(ValidationError::EmptyChunkWindow);
+            }
+        }
+        errs
+    }
+
+    /// Build the URL to send to the Quandl API, requesting the data in `format` (`json` or `csv`)
+    fn get_url(&self, format: &str) -> Result<Url> {
+        let mut url: Url = try!(Url::parse(&format!("{}/{}/{}/data.{}",
+                                                    QUANDL_BASE_URL,
+                                                    self.database_code,
+                                                    self.dataset_code,
+                                                    format)));
         let mut query: Vec<(&str, String)> = Vec::new();
 
         set_query_pair(&mut query, "api_key", &self.quandl.api_key);
@@ -227,19 +473,108 @@ impl<'a> QuandlRequest<'a> {
         set_query_pair(&mut query, "transform", &self.transform);
         url.set_query_from_pairs(query);
 
-        url
+        Ok(url)
     }
 
-    /// Make a request to the Quandl API with the specified parameters
+    /// Make a request to the Quandl API with the specified parameters. The request is validated
+    /// first (see `validate`). When `chunking` is set, one request is sent per window, and when
+    /// columns are selected by name, one request is sent per column.
     pub fn run(&self) -> Result<JsonValue> {
-        let url = self.get_url();
-        let res = try!(self.quandl.http_client.get(url).send());
+        try!(self.validate());
+        if !self.columns.is_empty() {
+            return column::run_named_columns(self);
+        }
+        match self.chunking {
+            Some(ref chunking) => chunk::run_chunked(self, chunking),
+            None => self.send_json(),
+        }
+    }
+
+    /// Make a request to the Quandl API and parse the response into a typed `Dataset`.
+    pub fn run_dataset(&self) -> Result<Dataset> {
+        let data = try!(self.run());
+        Dataset::from_json(&data)
+    }
+
+    /// Make a request to the Quandl API and deserialize each row into a `T` whose fields are
+    /// matched to the column names of the response. See `Dataset::deserialize_rows`.
+    pub fn run_as<T: Deserialize>(&self) -> Result<Vec<T>> {
+        let dataset = try!(self.run_dataset());
+        dataset.deserialize_rows()
+    }
+
+    /// Make a request to the Quandl API and decode each row into a `T` by position. See
+    /// `QuandlRow`.
+    pub fn run_typed<T: QuandlRow>(&self) -> Result<Vec<T>> {
+        let dataset = try!(self.run_dataset());
+        dataset.decode_rows()
+    }
+
+    /// Make a request to the Quandl API for CSV data and return an iterator that parses one row
+    /// at a time while the response is read, so large datasets are never held in memory.
+    /// `chunking` is not supported when streaming rows.
+    pub fn run_rows(&self) -> Result<RowIter<BufReader<Response>>> {
+        try!(self.validate_unchunked());
+        let res = try!(self.send("csv"));
+        RowIter::new(BufReader::new(res))
+    }
+
+    /// Like `run_rows`, but requesting JSON data. The response is scanned for the `data` array
+    /// and each row is parsed on its own, see `JsonRowIter`.
+    pub fn run_json_rows(&self) -> Result<JsonRowIter<BufReader<Response>>> {
+        try!(self.validate_unchunked());
+        let res = try!(self.send("json"));
+        JsonRowIter::new(BufReader::new(res))
+    }
+
+    /// Make a request to the Quandl API and return the status, headers and JSON body bytes
+    /// exactly as Quandl returned them, without parsing the body. Unlike `run`, a response with
+    /// an error status is returned as is. `chunking` is not supported.
+    pub fn run_raw(&self) -> Result<RawResponse> {
+        try!(self.validate_unchunked());
+        let mut res = try!(self.send_unchecked("json"));
+        let mut body = Vec::new();
+        try!(res.read_to_end(&mut body));
+        Ok(RawResponse {
+            status: res.status,
+            headers: res.headers.clone(),
+            body: body,
+        })
+    }
+
+    /// Make a request to the Quandl API and stream the JSON body, without parsing it, to
+    /// `writer`. Returns the number of bytes written. Fails without writing anything when Quandl
+    /// rejects the request. `chunking` is not supported.
+    pub fn run_to_writer<W: Write>(&self, writer: &mut W) -> Result<u64> {
+        try!(self.validate_unchunked());
+        let mut res = try!(self.send("json"));
+        Ok(try!(io::copy(&mut res, writer)))
+    }
+
+    /// Validate the request for methods that don't support `chunking` or named `columns`.
+    fn validate_unchunked(&self) -> Result<()> {
+        let mut errs = self.validation_errors();
+        if self.chunking.is_some() {
+            errs.push(ValidationError::ChunkingUnsupported);
+        }
+        if !self.columns.is_empty() {
+            errs.push(ValidationError::ColumnsUnsupported);
+        }
+        to_result(errs)
+    }
+
+    /// Send the request for data in `format` without checking the response status.
+    fn send_unchecked(&self, format: &str) -> Result<Response> {
+        let url = try!(self.get_url(format));
+        Ok(try!(self.quandl.http_client.get(url).send()))
+    }
+
+    /// Send the request for data in `format` and check the response status.
+    fn send(&self, format: &str) -> Result<Response> {
+        let res = try!(self.send_unchecked(format));
 
         match res.status {
-            hyper::Ok => {
-                let data: JsonValue = try!(serde_json::from_reader(res));
-                Ok(data)
-            }
+            hyper::Ok => Ok(res),
             // something happened, quandl rejected the request
             status => {
                 let data: JsonValue = try!(serde_json::from_reader(res));
@@ -251,6 +586,13 @@ impl<'a> QuandlRequest<'a> {
         }
     }
 
+    /// Send the request and parse the JSON response.
+    fn send_json(&self) -> Result<JsonValue> {
+        let res = try!(self.send("json"));
+        let data: JsonValue = try!(serde_json::from_reader(res));
+        Ok(data)
+    }
+
     /// Create a default QuandlRequest
     pub fn default(quandl: &'a Quandl) -> QuandlRequest<'a> {
         QuandlRequest {
@@ -260,15 +602,28 @@ impl<'a> QuandlRequest<'a> {
             limit: None,
             rows: None,
             column_index: None,
+            columns: Vec::new(),
             start_date: None,
             end_date: None,
             order: None,
             collapse: None,
             transform: None,
+            chunking: None,
         }
     }
 }
 
+/// Status, headers and body of a response, exactly as returned by Quandl.
+#[derive(Debug, Clone)]
+pub struct RawResponse {
+    /// HTTP status of the response
+    pub status: StatusCode,
+    /// HTTP headers of the response
+    pub headers: Headers,
+    /// Unparsed body of the response
+    pub body: Vec<u8>,
+}
+
 /// Allow for multiple types to be used as input to the `start_date` and `end_date` `QuandlRequest`
 /// parameters.
 pub trait DateInput {
@@ -280,11 +635,11 @@ pub trait DateInput {
 
 impl DateInput for str {
     fn set_start_date(&self, quandl_request: &mut QuandlRequest) -> Result<()> {
-        let date = try!(self.parse::<NaiveDate>());
+        let date = try!(self.parse::<DateBound>());
         date.set_start_date(quandl_request)
     }
     fn set_end_date(&self, quandl_request: &mut QuandlRequest) -> Result<()> {
-        let date = try!(self.parse::<NaiveDate>());
+        let date = try!(self.parse::<DateBound>());
         date.set_end_date(quandl_request)
     }
 }
@@ -314,6 +669,31 @@ fn validate_date(start_date: &Option<NaiveDate>, end_date: &Option<NaiveDate>) -
     Ok(())
 }
 
+/// `Error::Validation` with `errs`, if there are any.
+fn to_result(errs: Vec<ValidationError>) -> Result<()> {
+    if errs.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Validation(errs))
+    }
+}
+
+/// Find the variant whose `Display` spelling matches `s`, ignoring case and surrounding
+/// whitespace. The error lists all valid values.
+fn parse_variant<T: Copy + Display>(s: &str, name: &str, variants: &[T]) -> Result<T> {
+    let lower = s.trim().to_lowercase();
+    match variants.iter().find(|variant| variant.to_string() == lower) {
+        Some(variant) => Ok(*variant),
+        None => {
+            let valid: Vec<String> = variants.iter().map(|variant| variant.to_string()).collect();
+            Err(Error::Parse(format!("invalid {} `{}`, expected one of: {}",
+                                     name,
+                                     s,
+                                     valid.join(", "))))
+        }
+    }
+}
+
 /// Set query parameters for the given option if it is `Some(T)`
 fn set_query_pair<'a, T: Display>(query: &mut Vec<(&'a str, String)>,
                                   key: &'a str,
@@ -327,6 +707,8 @@ fn set_query_pair<'a, T: Display>(query: &mut Vec<(&'a str, String)>,
 mod tests {
     use super::*;
     use url::Url;
+    use serde_json;
+    use error::{Error, ValidationError};
     use super::super::{NaiveDate, Quandl};
 
     fn new_quandl_request(quandl: &Quandl) -> QuandlRequest {
@@ -363,6 +745,19 @@ mod tests {
         assert_eq!(qr.column_index, Some(0));
     }
 
+    #[test]
+    fn test_column() {
+        let q = Quandl::new();
+        let qr = new_quandl_request(&q).column("Open").column("Adj. Close");
+        assert_eq!(qr.columns, vec!["Open", "Adj. Close"]);
+        match qr.column_index(1).validate() {
+            Err(Error::Validation(errs)) => {
+                assert_eq!(errs, vec![ValidationError::ColumnIndexAndColumns]);
+            }
+            res => panic!("unexpected validation result: {:?}", res),
+        }
+    }
+
     #[test]
     fn test_order() {
         use super::Order::*;
@@ -387,6 +782,50 @@ mod tests {
         assert_eq!(qr.transform, Some(Rdiff));
     }
 
+    #[test]
+    fn test_enum_round_trip() {
+        use super::Collapse::*;
+        use super::Transform::*;
+        for collapse in &[Collapse::None, Daily, Weekly, Monthly, Quarterly, Annual] {
+            assert_eq!(collapse.to_string().parse::<Collapse>().unwrap(), *collapse);
+            let json = serde_json::to_string(collapse).unwrap();
+            assert_eq!(serde_json::from_str::<Collapse>(&json).unwrap(), *collapse);
+        }
+        for transform in &[Transform::None, Diff, Rdiff, RdiffFrom, Cumul, Normalize] {
+            assert_eq!(transform.to_string().parse::<Transform>().unwrap(), *transform);
+            let json = serde_json::to_string(transform).unwrap();
+            assert_eq!(serde_json::from_str::<Transform>(&json).unwrap(), *transform);
+        }
+        assert_eq!(Collapse::None.to_string(), "none");
+        assert_eq!(Transform::None.to_string(), "none");
+        assert_eq!(RdiffFrom.to_string(), "rdiff_from");
+    }
+
+    #[test]
+    fn test_enum_from_str() {
+        assert_eq!("desc".parse::<Order>().unwrap(), Order::Desc);
+        assert_eq!("ASC".parse::<Order>().unwrap(), Order::Asc);
+        assert_eq!("quarterly".parse::<Collapse>().unwrap(), Collapse::Quarterly);
+        assert_eq!(" Monthly ".parse::<Collapse>().unwrap(), Collapse::Monthly);
+        assert_eq!("cumul".parse::<Transform>().unwrap(), Transform::Cumul);
+        assert_eq!("RDIFF_From".parse::<Transform>().unwrap(), Transform::RdiffFrom);
+
+        match "hourly".parse::<Collapse>() {
+            Err(Error::Parse(msg)) => {
+                assert_eq!(msg,
+                           "invalid collapse `hourly`, expected one of: none, daily, weekly, \
+                            monthly, quarterly, annual");
+            }
+            res => panic!("unexpected parse result: {:?}", res),
+        }
+        assert!("sideways".parse::<Order>().is_err());
+        assert!("log".parse::<Transform>().is_err());
+
+        // serialized with the exact API spelling
+        assert_eq!(serde_json::to_string(&Transform::RdiffFrom).unwrap(), "\"rdiff_from\"");
+        assert_eq!(serde_json::from_str::<Order>("\"Desc\"").unwrap(), Order::Desc);
+    }
+
     #[test]
     fn test_url_query() {
         let u_str = "https://www.quandl.com/api/v3/datasets/WIKI/AAPL/data.\
@@ -406,7 +845,91 @@ mod tests {
                      .unwrap()
                      .end_date("2015-03-10")
                      .unwrap();
-        assert_eq!(qr.get_url(), url);
+        assert_eq!(qr.get_url("json").unwrap(), url);
+    }
+
+    #[test]
+    fn test_validate() {
+        let q = Quandl::new();
+        assert!(new_quandl_request(&q).limit(1).validate().is_ok());
+
+        let qr = q.new_request("", "AA PL").limit(0).rows(0);
+        match qr.validate() {
+            Err(Error::Validation(errs)) => {
+                assert_eq!(errs,
+                           vec![ValidationError::EmptyDatabaseCode,
+                                ValidationError::InvalidDatasetCode(String::from("AA PL")),
+                                ValidationError::LimitAndRows,
+                                ValidationError::ZeroLimit,
+                                ValidationError::ZeroRows]);
+            }
+            res => panic!("unexpected validation result: {:?}", res),
+        }
+        assert!(qr.run().is_err());
+
+        let mut qr = new_quandl_request(&q);
+        qr.start_date = Some(NaiveDate::from_ymd(2015, 3, 10));
+        qr.end_date = Some(NaiveDate::from_ymd(2015, 2, 10));
+        match qr.validate() {
+            Err(Error::Validation(errs)) => {
+                assert_eq!(errs,
+                           vec![ValidationError::StartAfterEnd(NaiveDate::from_ymd(2015, 3, 10),
+                                                               NaiveDate::from_ymd(2015, 2, 10))]);
+            }
+            res => panic!("unexpected validation result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn test_validate_unchunked() {
+        use super::super::{Chunking, ChunkWindow};
+        let q = Quandl::new();
+        let qr = new_quandl_request(&q)
+                     .start_date("2000-01-01")
+                     .unwrap()
+                     .end_date("2015-01-01")
+                     .unwrap()
+                     .column("Close")
+                     .chunking(Chunking::sequential(ChunkWindow::Years(1)));
+        assert!(qr.validate().is_ok());
+        // fails before sending anything
+        match qr.run_raw().err() {
+            Some(Error::Validation(errs)) => {
+                assert_eq!(errs,
+                           vec![ValidationError::ChunkingUnsupported,
+                                ValidationError::ColumnsUnsupported]);
+            }
+            res => panic!("unexpected validation result: {:?}", res),
+        }
+        assert!(qr.limit(0).run_to_writer(&mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_validate_chunking() {
+        use super::super::{Chunking, ChunkWindow};
+        let q = Quandl::new();
+        let qr = new_quandl_request(&q)
+                     .start_date("2000-01-01")
+                     .unwrap()
+                     .end_date("2015-01-01")
+                     .unwrap()
+                     .chunking(Chunking::parallel(ChunkWindow::Years(1), 4));
+        assert!(qr.validate().is_ok());
+
+        let qr = new_quandl_request(&q)
+                     .rows(5)
+                     .transform(Transform::Diff)
+                     .chunking(Chunking::sequential(ChunkWindow::Years(0)));
+        match qr.validate() {
+            Err(Error::Validation(errs)) => {
+                assert_eq!(errs,
+                           vec![ValidationError::ChunkingWithoutDateRange,
+                                ValidationError::ChunkingWithLimit,
+                                ValidationError::ChunkingWithTransform,
+                                ValidationError::EmptyChunkWindow]);
+            }
+            res => panic!("unexpected validation result: {:?}", res),
+        }
     }
 
     #[test]
@@ -476,6 +999,28 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "test-quandl-api")]
+    #[test]
+    fn test_quandl_raw() {
+        let q = Quandl::new();
+        let raw = new_quandl_request(&q).rows(1u64).run_raw().unwrap();
+        assert_eq!(raw.status, ::hyper::Ok);
+
+        let mut body = Vec::new();
+        let written = new_quandl_request(&q).rows(1u64).run_to_writer(&mut body).unwrap();
+        assert_eq!(written, body.len() as u64);
+        assert!(::serde_json::from_slice::<JsonValue>(&body).is_ok());
+    }
+
+    #[cfg(feature = "test-quandl-api")]
+    #[test]
+    fn test_quandl_bars() {
+        let q = Quandl::new();
+        let bars = q.bars("WIKI/AAPL").unwrap();
+        assert!(!bars.is_empty());
+        assert!(bars[0].adj_close.is_some());
+    }
+
     #[cfg(feature = "test-quandl-api")]
     #[test]
     fn test_quandl_works() {
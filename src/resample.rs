@@ -0,0 +1,277 @@
+use chrono::{Datelike, Duration};
+use calendar::Calendar;
+use date::add_months;
+use super::{NaiveDate, Row, Dataset, Collapse};
+
+/// How the rows of a period are combined by `resample`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Aggregation {
+    /// The last row of the period, like Quandl's `collapse`
+    Last,
+    /// The first row of the period
+    First,
+    /// The mean of the non-missing values of each column
+    Mean,
+    /// The sum of the non-missing values of each column
+    Sum,
+    /// Open, high, low and close of the non-missing values of each column. Each column becomes
+    /// four columns.
+    Ohlc,
+}
+
+/// Last day of the period of `collapse` containing `date`. Weeks end on Sunday. Quandl uses
+/// this date for the rows of a collapsed dataset. `Collapse::None` keeps every date.
+pub fn period_end(date: NaiveDate, collapse: Collapse) -> NaiveDate {
+    match collapse {
+        Collapse::None | Collapse::Daily => date,
+        Collapse::Weekly => {
+            date + Duration::days(6 - date.weekday().num_days_from_monday() as i64)
+        }
+        Collapse::Monthly => month_end(date.year(), date.month()),
+        Collapse::Quarterly => month_end(date.year(), (date.month0() / 3 + 1) * 3),
+        Collapse::Annual => NaiveDate::from_ymd(date.year(), 12, 31),
+    }
+}
+
+fn month_end(year: i32, month: u32) -> NaiveDate {
+    match add_months(NaiveDate::from_ymd(year, month, 1), 1) {
+        Ok(next) => next - Duration::days(1),
+        // only the month after the last December of `NaiveDate` is out of range
+        Err(_) => NaiveDate::from_ymd(year, 12, 31),
+    }
+}
+
+/// Collapse rows to a lower frequency exactly like Quandl's `collapse` parameter: the last row of
+/// each period, dated with the end of the period.
+pub fn collapse(rows: &[Row], collapse: Collapse) -> Vec<Row> {
+    resample(rows, collapse, Aggregation::Last)
+}
+
+/// Resample rows to the frequency of `collapse`, combining the rows of each period with
+/// `aggregation`. Each resulting row is dated with the end of its period (see `period_end`).
+/// Rows may be in ascending or descending order; the result is in the same order.
+pub fn resample(rows: &[Row], collapse: Collapse, aggregation: Aggregation) -> Vec<Row> {
+    let mut sorted: Vec<&Row> = rows.iter().collect();
+    sorted.sort_by(|a, b| a.date.cmp(&b.date));
+
+    let mut result = Vec::new();
+    let mut start = 0;
+    while start < sorted.len() {
+        let end_date = period_end(sorted[start].date, collapse);
+        let mut end = start;
+        while end < sorted.len() && sorted[end].date <= end_date {
+            end += 1;
+        }
+        result.push(Row::new(end_date, aggregate(&sorted[start..end], aggregation)));
+        start = end;
+    }
+
+    if rows.len() > 1 && rows[0].date > rows[rows.len() - 1].date {
+        result.reverse();
+    }
+    result
+}
+
+/// Like `resample`, but each row is dated with the last business day of its period in
+/// `calendar` (see `Calendar::period_end`), e.g. the last trading day of each month.
+pub fn resample_business(rows: &[Row],
+                         collapse: Collapse,
+                         aggregation: Aggregation,
+                         calendar: &Calendar)
+                         -> Vec<Row> {
+    resample(rows, collapse, aggregation)
+        .into_iter()
+        .map(|row| Row::new(calendar.period_end(row.date, collapse), row.values))
+        .collect()
+}
+
+/// Resample a dataset with `resample`. With `Aggregation::Ohlc`, each value column `name` is
+/// replaced by the columns `name Open`, `name High`, `name Low` and `name Close`.
+pub fn resample_dataset(dataset: &Dataset,
+                        collapse: Collapse,
+                        aggregation: Aggregation)
+                        -> Dataset {
+    let column_names = match aggregation {
+        Aggregation::Ohlc => {
+            let mut names: Vec<String> = dataset.column_names.iter().take(1).cloned().collect();
+            for name in dataset.column_names.iter().skip(1) {
+                for suffix in &["Open", "High", "Low", "Close"] {
+                    names.push(format!("{} {}", name, suffix));
+                }
+            }
+            names
+        }
+        _ => dataset.column_names.clone(),
+    };
+    Dataset {
+        column_names: column_names,
+        rows: resample(&dataset.rows, collapse, aggregation),
+    }
+}
+
+/// Combine the chronologically sorted rows of one period.
+fn aggregate(rows: &[&Row], aggregation: Aggregation) -> Vec<Option<f64>> {
+    match aggregation {
+        Aggregation::Last => rows[rows.len() - 1].values.clone(),
+        Aggregation::First => rows[0].values.clone(),
+        Aggregation::Mean => {
+            columns(rows)
+                .into_iter()
+                .map(|column| {
+                    if column.is_empty() {
+                        None
+                    } else {
+                        Some(column.iter().fold(0.0, |sum, v| sum + v) / column.len() as f64)
+                    }
+                })
+                .collect()
+        }
+        Aggregation::Sum => {
+            columns(rows)
+                .into_iter()
+                .map(|column| {
+                    if column.is_empty() {
+                        None
+                    } else {
+                        Some(column.iter().fold(0.0, |sum, v| sum + v))
+                    }
+                })
+                .collect()
+        }
+        Aggregation::Ohlc => {
+            let mut values = Vec::new();
+            for column in columns(rows) {
+                if column.is_empty() {
+                    values.extend(vec![None; 4]);
+                } else {
+                    values.push(Some(column[0]));
+                    values.push(Some(column.iter().cloned().fold(::std::f64::MIN, f64::max)));
+                    values.push(Some(column.iter().cloned().fold(::std::f64::MAX, f64::min)));
+                    values.push(Some(column[column.len() - 1]));
+                }
+            }
+            values
+        }
+    }
+}
+
+/// The non-missing values of each column, in the order of the rows.
+fn columns(rows: &[&Row]) -> Vec<Vec<f64>> {
+    let width = rows.iter().map(|row| row.values.len()).max().unwrap_or(0);
+    let mut columns = vec![Vec::new(); width];
+    for row in rows {
+        for (column, value) in columns.iter_mut().zip(row.values.iter()) {
+            if let Some(value) = *value {
+                column.push(value);
+            }
+        }
+    }
+    columns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{Calendar, Dataset, NaiveDate, Row, Collapse};
+
+    fn ymd(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd(y, m, d)
+    }
+
+    fn rows() -> Vec<Row> {
+        // descending, like Quandl
+        vec![Row::new(ymd(2015, 4, 1), vec![Some(6.0)]),
+             Row::new(ymd(2015, 3, 31), vec![Some(5.0)]),
+             Row::new(ymd(2015, 2, 2), vec![None]),
+             Row::new(ymd(2015, 1, 30), vec![Some(4.0)]),
+             Row::new(ymd(2015, 1, 5), vec![Some(1.0)]),
+             Row::new(ymd(2015, 1, 4), vec![Some(3.0)]),
+             Row::new(ymd(2015, 1, 2), vec![Some(2.0)])]
+    }
+
+    #[test]
+    fn test_period_end() {
+        // 2015-01-07 is a Wednesday
+        assert_eq!(period_end(ymd(2015, 1, 7), Collapse::None), ymd(2015, 1, 7));
+        assert_eq!(period_end(ymd(2015, 1, 7), Collapse::Daily), ymd(2015, 1, 7));
+        assert_eq!(period_end(ymd(2015, 1, 7), Collapse::Weekly), ymd(2015, 1, 11));
+        assert_eq!(period_end(ymd(2015, 1, 11), Collapse::Weekly), ymd(2015, 1, 11));
+        assert_eq!(period_end(ymd(2015, 1, 12), Collapse::Weekly), ymd(2015, 1, 18));
+        assert_eq!(period_end(ymd(2016, 2, 7), Collapse::Monthly), ymd(2016, 2, 29));
+        assert_eq!(period_end(ymd(2015, 12, 7), Collapse::Monthly), ymd(2015, 12, 31));
+        assert_eq!(period_end(ymd(2015, 5, 7), Collapse::Quarterly), ymd(2015, 6, 30));
+        assert_eq!(period_end(ymd(2015, 12, 7), Collapse::Quarterly), ymd(2015, 12, 31));
+        assert_eq!(period_end(ymd(2015, 5, 7), Collapse::Annual), ymd(2015, 12, 31));
+    }
+
+    #[test]
+    fn test_collapse_last() {
+        assert_eq!(collapse(&rows(), Collapse::Monthly),
+                   vec![Row::new(ymd(2015, 4, 30), vec![Some(6.0)]),
+                        Row::new(ymd(2015, 3, 31), vec![Some(5.0)]),
+                        Row::new(ymd(2015, 2, 28), vec![None]),
+                        Row::new(ymd(2015, 1, 31), vec![Some(4.0)])]);
+
+        let mut asc = rows();
+        asc.reverse();
+        assert_eq!(collapse(&asc, Collapse::Weekly),
+                   vec![Row::new(ymd(2015, 1, 4), vec![Some(3.0)]),
+                        Row::new(ymd(2015, 1, 11), vec![Some(1.0)]),
+                        Row::new(ymd(2015, 2, 1), vec![Some(4.0)]),
+                        Row::new(ymd(2015, 2, 8), vec![None]),
+                        Row::new(ymd(2015, 4, 5), vec![Some(6.0)])]);
+    }
+
+    #[test]
+    fn test_collapse_none() {
+        assert_eq!(collapse(&rows(), Collapse::None), rows());
+    }
+
+    #[test]
+    fn test_aggregations() {
+        let rows = rows();
+        assert_eq!(resample(&rows, Collapse::Quarterly, Aggregation::First),
+                   vec![Row::new(ymd(2015, 6, 30), vec![Some(6.0)]),
+                        Row::new(ymd(2015, 3, 31), vec![Some(2.0)])]);
+        assert_eq!(resample(&rows, Collapse::Quarterly, Aggregation::Sum),
+                   vec![Row::new(ymd(2015, 6, 30), vec![Some(6.0)]),
+                        Row::new(ymd(2015, 3, 31), vec![Some(15.0)])]);
+        assert_eq!(resample(&rows, Collapse::Quarterly, Aggregation::Mean),
+                   vec![Row::new(ymd(2015, 6, 30), vec![Some(6.0)]),
+                        Row::new(ymd(2015, 3, 31), vec![Some(3.0)])]);
+        assert_eq!(resample(&rows, Collapse::Monthly, Aggregation::Mean)[2],
+                   Row::new(ymd(2015, 2, 28), vec![None]));
+        assert_eq!(resample(&rows, Collapse::Annual, Aggregation::Ohlc),
+                   vec![Row::new(ymd(2015, 12, 31),
+                                 vec![Some(2.0), Some(6.0), Some(1.0), Some(6.0)])]);
+        assert!(resample(&[], Collapse::Annual, Aggregation::Last).is_empty());
+    }
+
+    #[test]
+    fn test_resample_business() {
+        let nyse = Calendar::nyse();
+        assert_eq!(resample_business(&rows(), Collapse::Monthly, Aggregation::Last, &nyse)
+                       .iter()
+                       .map(|row| row.date)
+                       .collect::<Vec<_>>(),
+                   vec![ymd(2015, 4, 30), ymd(2015, 3, 31), ymd(2015, 2, 27), ymd(2015, 1, 30)]);
+        // the week of Good Friday ends on Thursday
+        assert_eq!(resample_business(&rows(), Collapse::Weekly, Aggregation::Last, &nyse)[0],
+                   Row::new(ymd(2015, 4, 2), vec![Some(6.0)]));
+        assert_eq!(resample_business(&rows(), Collapse::None, Aggregation::Last, &nyse), rows());
+    }
+
+    #[test]
+    fn test_resample_dataset() {
+        let dataset = Dataset {
+            column_names: vec![String::from("Date"), String::from("Close")],
+            rows: rows(),
+        };
+        let resampled = resample_dataset(&dataset, Collapse::Annual, Aggregation::Ohlc);
+        assert_eq!(resampled.column_names,
+                   vec!["Date", "Close Open", "Close High", "Close Low", "Close Close"]);
+        assert_eq!(resampled.rows.len(), 1);
+        assert_eq!(resample_dataset(&dataset, Collapse::Annual, Aggregation::Last).column_names,
+                   dataset.column_names);
+    }
+}
@@ -0,0 +1,661 @@
+use std::collections::BTreeMap;
+use std::io::BufRead;
+use std::str;
+use serde::Deserialize;
+use serde_json;
+use error::{Error, Result};
+use typed::QuandlRow;
+use super::{NaiveDate, JsonValue};
+
+/// A single observation of a dataset: the date column followed by the values of the other
+/// columns, `None` where Quandl returned no value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    /// Date of the observation
+    pub date: NaiveDate,
+    /// Values of the data columns, in the order of the column names after the date column
+    pub values: Vec<Option<f64>>,
+}
+
+impl Row {
+    /// Creates a row with the given date and values.
+    pub fn new(date: NaiveDate, values: Vec<Option<f64>>) -> Row {
+        Row {
+            date: date,
+            values: values,
+        }
+    }
+
+    /// Parse a row of the `data` array of a JSON response.
+    pub fn from_json(value: &JsonValue) -> Result<Row> {
+        let invalid = || Error::Parse(format!("invalid dataset row `{:?}`", value));
+        let fields = match value.as_array() {
+            Some(fields) if !fields.is_empty() => fields,
+            _ => return Err(invalid()),
+        };
+        let date = match fields[0].as_string() {
+            Some(date) => try!(date.parse::<NaiveDate>()),
+            None => return Err(invalid()),
+        };
+        let mut values = Vec::with_capacity(fields.len() - 1);
+        for field in &fields[1..] {
+            match *field {
+                JsonValue::Null => values.push(None),
+                ref field => {
+                    match field.as_f64() {
+                        Some(value) => values.push(Some(value)),
+                        None => return Err(invalid()),
+                    }
+                }
+            }
+        }
+        Ok(Row::new(date, values))
+    }
+}
+
+/// Column names and rows of a dataset.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Dataset {
+    /// Names of the columns, starting with the date column
+    pub column_names: Vec<String>,
+    /// Rows in the order returned by Quandl
+    pub rows: Vec<Row>,
+}
+
+impl Dataset {
+    /// Parse the `dataset_data` of a response returned by `QuandlRequest::run`.
+    pub fn from_json(value: &JsonValue) -> Result<Dataset> {
+        let unexpected = || {
+            Error::Parse(String::from("unexpected response format: expected `dataset_data` with \
+                                       `column_names` and `data`"))
+        };
+        let column_names = match value.lookup("dataset_data.column_names")
+                                      .and_then(|names| names.as_array()) {
+            Some(names) => names,
+            None => return Err(unexpected()),
+        };
+        let data = match value.lookup("dataset_data.data").and_then(|data| data.as_array()) {
+            Some(data) => data,
+            None => return Err(unexpected()),
+        };
+
+        let mut dataset = Dataset::default();
+        for name in column_names {
+            match name.as_string() {
+                Some(name) => dataset.column_names.push(String::from(name)),
+                None => return Err(unexpected()),
+            }
+        }
+        for row in data {
+            dataset.rows.push(try!(Row::from_json(row)));
+        }
+        Ok(dataset)
+    }
+
+    /// Index of the column called `name` among the values of a `Row`, i.e. not counting the
+    /// date column.
+    pub fn value_index(&self, name: &str) -> Option<usize> {
+        self.column_names.iter().skip(1).position(|column| column == name)
+    }
+
+    /// Deserialize each row into a `T`. The row is presented to `T` as a map from column name to
+    /// value, so the fields of `T` are matched by column name rather than by position. Names
+    /// like `Adj. Close` can be matched with serde's `rename` attribute. The date is a
+    /// `yyyy-mm-dd` string and missing values are `null`, so use `Option<f64>` for columns that
+    /// may have gaps.
+    pub fn deserialize_rows<T: Deserialize>(&self) -> Result<Vec<T>> {
+        let mut result = Vec::with_capacity(self.rows.len());
+        for row in &self.rows {
+            let mut fields = BTreeMap::new();
+            if let Some(name) = self.column_names.first() {
+                fields.insert(name.clone(), JsonValue::String(row.date.to_string()));
+            }
+            for (name, value) in self.column_names.iter().skip(1).zip(row.values.iter()) {
+                let value = match *value {
+                    Some(value) => JsonValue::F64(value),
+                    None => JsonValue::Null,
+                };
+                fields.insert(name.clone(), value);
+            }
+            match serde_json::from_value(JsonValue::Object(fields)) {
+                Ok(value) => result.push(value),
+                Err(err) => {
+                    return Err(Error::Parse(format!("can't deserialize row `{}` with columns \
+                                                     {}: {}",
+                                                    row.date,
+                                                    self.column_names.join(", "),
+                                                    err)))
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Decode each row into a `T` by position, resolving the column names only once. This is
+    /// faster than `deserialize_rows` for large datasets.
+    pub fn decode_rows<T: QuandlRow>(&self) -> Result<Vec<T>> {
+        let layout = try!(T::layout(&self.column_names));
+        self.rows.iter().map(|row| T::decode(&layout, row)).collect()
+    }
+}
+
+/// Iterator over the rows of a CSV response. Rows are parsed one line at a time, so the whole
+/// response is never held in memory. Created with `QuandlRequest::run_rows`, or with `new` over
+/// any `BufRead` containing Quandl CSV data.
+#[derive(Debug)]
+pub struct RowIter<R> {
+    reader: R,
+    column_names: Vec<String>,
+    line: String,
+}
+
+impl<R: BufRead> RowIter<R> {
+    /// Read the header line of the CSV data in `reader`.
+    pub fn new(mut reader: R) -> Result<RowIter<R>> {
+        let mut line = String::new();
+        try!(reader.read_line(&mut line));
+        let column_names = split_csv_line(line.trim_right_matches(&['\r', '\n'][..]));
+        if column_names.is_empty() || column_names[0].is_empty() {
+            return Err(Error::Parse(String::from("missing CSV header")));
+        }
+        Ok(RowIter {
+            reader: reader,
+            column_names: column_names,
+            line: line,
+        })
+    }
+
+    /// Names of the columns from the CSV header, starting with the date column.
+    pub fn column_names(&self) -> &[String] {
+        &self.column_names
+    }
+
+    /// Read all remaining rows into a `Dataset`.
+    pub fn into_dataset(self) -> Result<Dataset> {
+        let column_names = self.column_names.clone();
+        let mut rows = Vec::new();
+        for row in self {
+            rows.push(try!(row));
+        }
+        Ok(Dataset {
+            column_names: column_names,
+            rows: rows,
+        })
+    }
+
+    fn parse_line(&self) -> Result<Row> {
+        let line = self.line.trim_right_matches(&['\r', '\n'][..]);
+        let invalid = || Error::Parse(format!("invalid CSV row `{}`", line));
+        let fields = split_csv_line(line);
+        if fields.len() != self.column_names.len() {
+            return Err(invalid());
+        }
+        let date = try!(fields[0].parse::<NaiveDate>());
+        let mut values = Vec::with_capacity(fields.len() - 1);
+        for field in &fields[1..] {
+            if field.is_empty() {
+                values.push(None);
+            } else {
+                values.push(Some(try!(field.parse::<f64>().map_err(|_| invalid()))));
+            }
+        }
+        Ok(Row::new(date, values))
+    }
+}
+
+impl<R: BufRead> Iterator for RowIter<R> {
+    type Item = Result<Row>;
+
+    fn next(&mut self) -> Option<Result<Row>> {
+        loop {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(err) => return Some(Err(Error::from(err))),
+            }
+            // skip blank lines, e.g. a trailing newline
+            if !self.line.trim().is_empty() {
+                return Some(self.parse_line());
+            }
+        }
+    }
+}
+
+/// Iterator over the rows of a JSON response. The response is scanned up to the `data` array and
+/// each row is parsed on its own, so the whole response is never held in memory. Created with
+/// `QuandlRequest::run_json_rows`, or with `new` over any `BufRead` containing a Quandl JSON
+/// response. Quandl sends `column_names` before `data`; a response with `data` first can't be
+/// streamed and is rejected.
+#[derive(Debug)]
+pub struct JsonRowIter<R> {
+    reader: R,
+    column_names: Vec<String>,
+    started: bool,
+    done: bool,
+}
+
+impl<R: BufRead> JsonRowIter<R> {
+    /// Read the JSON response in `reader` up to the start of the `data` array.
+    pub fn new(reader: R) -> Result<JsonRowIter<R>> {
+        let unexpected = || {
+            Error::Parse(String::from("unexpected response format: expected `dataset_data` with \
+                                       `column_names` followed by `data`"))
+        };
+        let mut rows = JsonRowIter {
+            reader: reader,
+            column_names: Vec::new(),
+            started: false,
+            done: false,
+        };
+        try!(rows.expect(b'{'));
+        loop {
+            match try!(rows.next_key()) {
+                Some(ref key) if key == "dataset_data" => break,
+                Some(_) => {
+                    try!(rows.read_value());
+                }
+                None => return Err(unexpected()),
+            }
+        }
+        try!(rows.expect(b'{'));
+        let mut column_names = None;
+        loop {
+            match try!(rows.next_key()) {
+                Some(ref key) if key == "column_names" => {
+                    let names = try!(rows.parse_value());
+                    let names: Option<Vec<String>> = names.as_array().and_then(|names| {
+                        names.iter().map(|name| name.as_string().map(String::from)).collect()
+                    });
+                    match names {
+                        Some(names) => column_names = Some(names),
+                        None => return Err(unexpected()),
+                    }
+                }
+                Some(ref key) if key == "data" => break,
+                Some(_) => {
+                    try!(rows.read_value());
+                }
+                None => return Err(unexpected()),
+            }
+        }
+        match column_names {
+            Some(names) => rows.column_names = names,
+            None => return Err(unexpected()),
+        }
+        try!(rows.expect(b'['));
+        Ok(rows)
+    }
+
+    /// Names of the columns from `column_names`, starting with the date column.
+    pub fn column_names(&self) -> &[String] {
+        &self.column_names
+    }
+
+    /// Read all remaining rows into a `Dataset`.
+    pub fn into_dataset(self) -> Result<Dataset> {
+        let column_names = self.column_names.clone();
+        let mut rows = Vec::new();
+        for row in self {
+            rows.push(try!(row));
+        }
+        Ok(Dataset {
+            column_names: column_names,
+            rows: rows,
+        })
+    }
+
+    /// Next element of the `data` array, or `None` at its end.
+    fn next_value(&mut self) -> Result<Option<JsonValue>> {
+        try!(self.skip_whitespace());
+        if try!(self.peek()) == Some(b']') {
+            self.reader.consume(1);
+            return Ok(None);
+        }
+        if self.started {
+            try!(self.expect(b','));
+        }
+        self.started = true;
+        self.parse_value().map(Some)
+    }
+
+    /// Next key of an object whose `{` or previous value has been read, or `None` at its end.
+    fn next_key(&mut self) -> Result<Option<String>> {
+        try!(self.skip_whitespace());
+        match try!(self.peek()) {
+            Some(b'}') => {
+                self.reader.consume(1);
+                return Ok(None);
+            }
+            Some(b',') => self.reader.consume(1),
+            _ => {}
+        }
+        let key = match try!(self.parse_value()) {
+            JsonValue::String(key) => key,
+            key => return Err(Error::Parse(format!("invalid JSON object key `{:?}`", key))),
+        };
+        try!(self.expect(b':'));
+        Ok(Some(key))
+    }
+
+    fn parse_value(&mut self) -> Result<JsonValue> {
+        let value = try!(self.read_value());
+        match str::from_utf8(&value) {
+            Ok(value) => Ok(try!(serde_json::from_str(value))),
+            Err(err) => Err(Error::Parse(format!("invalid JSON response: {}", err))),
+        }
+    }
+
+    /// Read the bytes of the next JSON value without parsing it, up to the `,`, `]` or `}`
+    /// following a number or literal.
+    fn read_value(&mut self) -> Result<Vec<u8>> {
+        try!(self.skip_whitespace());
+        let mut value = Vec::new();
+        let mut depth = 0;
+        let mut in_string = false;
+        let mut escaped = false;
+        loop {
+            let byte = match try!(self.peek()) {
+                Some(byte) => byte,
+                None if value.is_empty() || depth > 0 || in_string => return Err(truncated()),
+                None => break,
+            };
+            if in_string {
+                value.push(byte);
+                self.reader.consume(1);
+                if escaped {
+                    escaped = false;
+                } else if byte == b'\\' {
+                    escaped = true;
+                } else if byte == b'"' {
+                    in_string = false;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                continue;
+            }
+            match byte {
+                b'"' => in_string = true,
+                b'[' | b'{' => depth += 1,
+                b']' | b'}' if depth > 0 => depth -= 1,
+                b',' | b']' | b'}' | b' ' | b'\t' | b'\r' | b'\n' if depth == 0 => break,
+                _ => {}
+            }
+            value.push(byte);
+            self.reader.consume(1);
+            if depth == 0 && (byte == b']' || byte == b'}') {
+                break;
+            }
+        }
+        Ok(value)
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<()> {
+        try!(self.skip_whitespace());
+        match try!(self.peek()) {
+            Some(byte) if byte == expected => {
+                self.reader.consume(1);
+                Ok(())
+            }
+            Some(byte) => {
+                Err(Error::Parse(format!("invalid JSON response: expected `{}`, found `{}`",
+                                         expected as char,
+                                         byte as char)))
+            }
+            None => Err(truncated()),
+        }
+    }
+
+    fn skip_whitespace(&mut self) -> Result<()> {
+        while let Some(byte) = try!(self.peek()) {
+            match byte {
+                b' ' | b'\t' | b'\r' | b'\n' => self.reader.consume(1),
+                _ => break,
+            }
+        }
+        Ok(())
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>> {
+        let buf = try!(self.reader.fill_buf());
+        Ok(buf.first().cloned())
+    }
+}
+
+impl<R: BufRead> Iterator for JsonRowIter<R> {
+    type Item = Result<Row>;
+
+    fn next(&mut self) -> Option<Result<Row>> {
+        if self.done {
+            return None;
+        }
+        // an invalid row is returned as an error and the iteration goes on with the next row, like
+        // `RowIter`, but malformed JSON ends the iteration since the next row can't be found
+        match self.next_value() {
+            Ok(Some(value)) => Some(Row::from_json(&value)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+fn truncated() -> Error {
+    Error::Parse(String::from("invalid JSON response: unexpected end of data"))
+}
+
+/// Split a CSV line into fields, handling double quoted fields with `""` escapes.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(::std::mem::replace(&mut field, String::new())),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use serde::{de, Deserialize, Deserializer};
+    use serde_json;
+    use super::*;
+    use super::split_csv_line;
+    use super::super::{NaiveDate, JsonValue};
+
+    #[test]
+    fn test_split_csv_line() {
+        assert_eq!(split_csv_line("Date,Open,\"Adj. Close\""),
+                   vec!["Date", "Open", "Adj. Close"]);
+        assert_eq!(split_csv_line("a,\"b,c\",\"d\"\"e\",,"),
+                   vec!["a", "b,c", "d\"e", "", ""]);
+    }
+
+    #[test]
+    fn test_row_iter() {
+        let csv = "Date,Open,Close\r\n2015-01-02,1.5,2.5\r\n2015-01-01,,3\r\n\r\n";
+        let mut rows = RowIter::new(Cursor::new(csv)).unwrap();
+        assert_eq!(rows.column_names(), &["Date", "Open", "Close"]);
+        assert_eq!(rows.next().unwrap().unwrap(),
+                   Row::new(NaiveDate::from_ymd(2015, 1, 2), vec![Some(1.5), Some(2.5)]));
+        assert_eq!(rows.next().unwrap().unwrap(),
+                   Row::new(NaiveDate::from_ymd(2015, 1, 1), vec![None, Some(3.0)]));
+        assert!(rows.next().is_none());
+    }
+
+    #[test]
+    fn test_row_iter_errors() {
+        assert!(RowIter::new(Cursor::new("")).is_err());
+
+        let csv = "Date,Close\n2015-01-02,abc\n2015-01-01\n2015-13-01,1\n2014-12-31,1\n";
+        let rows: Vec<_> = RowIter::new(Cursor::new(csv)).unwrap().collect();
+        assert_eq!(rows.len(), 4);
+        assert!(rows[0].is_err());
+        assert!(rows[1].is_err());
+        assert!(rows[2].is_err());
+        assert!(rows[3].is_ok());
+    }
+
+    #[test]
+    fn test_json_row_iter() {
+        let json = "{\"dataset_data\": {\"limit\": null, \"transform\": \"none\", \
+                    \"column_names\": [\"Date\", \"Open\", \"Close\"], \
+                    \"note\": {\"a\": [\"]}\\\"\", 1]}, \
+                    \"data\": [[\"2015-01-02\", 1.5, 2.5],\n[\"2015-01-01\", null, 3], \
+                    [\"2014-12-31\", \"abc\", 3], [\"2014-12-30\", 1, 2]], \
+                    \"order\": null}}";
+        let mut rows = JsonRowIter::new(Cursor::new(json)).unwrap();
+        assert_eq!(rows.column_names(), &["Date", "Open", "Close"]);
+        assert_eq!(rows.next().unwrap().unwrap(),
+                   Row::new(NaiveDate::from_ymd(2015, 1, 2), vec![Some(1.5), Some(2.5)]));
+        assert_eq!(rows.next().unwrap().unwrap(),
+                   Row::new(NaiveDate::from_ymd(2015, 1, 1), vec![None, Some(3.0)]));
+        // an invalid row is returned as an error without ending the iteration
+        assert!(rows.next().unwrap().is_err());
+        assert!(rows.next().unwrap().is_ok());
+        assert!(rows.next().is_none());
+
+        let json = "{\"dataset_data\":{\"column_names\":[\"Date\",\"Close\"],\"data\":[]}}";
+        assert_eq!(JsonRowIter::new(Cursor::new(json)).unwrap().into_dataset().unwrap(),
+                   Dataset {
+                       column_names: vec![String::from("Date"), String::from("Close")],
+                       rows: Vec::new(),
+                   });
+    }
+
+    #[test]
+    fn test_json_row_iter_errors() {
+        assert!(JsonRowIter::new(Cursor::new("")).is_err());
+        assert!(JsonRowIter::new(Cursor::new("{\"dataset\":{}}")).is_err());
+        // `data` before `column_names` can't be streamed
+        let json = "{\"dataset_data\":{\"data\":[],\"column_names\":[\"Date\"]}}";
+        assert!(JsonRowIter::new(Cursor::new(json)).is_err());
+
+        // malformed JSON ends the iteration
+        let json = "{\"dataset_data\":{\"column_names\":[\"Date\",\"Close\"],\"data\":\
+                    [[\"2015-01-02\",1] [\"2015-01-01\",2]]}}";
+        let rows: Vec<_> = JsonRowIter::new(Cursor::new(json)).unwrap().collect();
+        assert_eq!(rows.len(), 2);
+        assert!(rows[0].is_ok());
+        assert!(rows[1].is_err());
+
+        let json = "{\"dataset_data\":{\"column_names\":[\"Date\",\"Close\"],\"data\":\
+                    [[\"2015-01-02\",1],[\"2015-01";
+        let rows: Vec<_> = JsonRowIter::new(Cursor::new(json)).unwrap().collect();
+        assert_eq!(rows.len(), 2);
+        assert!(rows[1].is_err());
+    }
+
+    #[test]
+    fn test_dataset_from_json() {
+        let json: JsonValue = serde_json::from_str("{\"dataset_data\":{\"column_names\":\
+                                                    [\"Date\",\"Open\",\"Close\"],\"data\":\
+                                                    [[\"2015-01-02\",1.5,2],\
+                                                    [\"2015-01-01\",null,3.5]]}}")
+                                  .unwrap();
+        let dataset = Dataset::from_json(&json).unwrap();
+        assert_eq!(dataset.column_names, vec!["Date", "Open", "Close"]);
+        assert_eq!(dataset.rows,
+                   vec![Row::new(NaiveDate::from_ymd(2015, 1, 2), vec![Some(1.5), Some(2.0)]),
+                        Row::new(NaiveDate::from_ymd(2015, 1, 1), vec![None, Some(3.5)])]);
+        assert_eq!(dataset.value_index("Close"), Some(1));
+        assert_eq!(dataset.value_index("Date"), None);
+
+        let csv = "Date,Open,Close\n2015-01-02,1.5,2\n2015-01-01,,3.5\n";
+        assert_eq!(RowIter::new(Cursor::new(csv)).unwrap().into_dataset().unwrap(), dataset);
+
+        let json: JsonValue = serde_json::from_str("{\"dataset_data\":{}}").unwrap();
+        assert!(Dataset::from_json(&json).is_err());
+    }
+
+    /// What `#[derive(Deserialize)]` with `#[serde(rename = "Adj. Close")]` would generate.
+    #[derive(Debug, PartialEq)]
+    struct Price {
+        date: String,
+        adj_close: Option<f64>,
+    }
+
+    impl Deserialize for Price {
+        fn deserialize<D: Deserializer>(deserializer: &mut D) -> ::std::result::Result<Price,
+                                                                                      D::Error> {
+            deserializer.visit_struct("Price", &["Date", "Adj. Close"], PriceVisitor)
+        }
+    }
+
+    struct PriceVisitor;
+
+    impl de::Visitor for PriceVisitor {
+        type Value = Price;
+
+        fn visit_map<V>(&mut self, mut visitor: V) -> ::std::result::Result<Price, V::Error>
+            where V: de::MapVisitor
+        {
+            let mut date = None;
+            let mut adj_close = None;
+            while let Some(key) = try!(visitor.visit_key::<String>()) {
+                match &key[..] {
+                    "Date" => date = Some(try!(visitor.visit_value())),
+                    "Adj. Close" => adj_close = Some(try!(visitor.visit_value())),
+                    _ => {
+                        try!(visitor.visit_value::<JsonValue>());
+                    }
+                }
+            }
+            try!(visitor.end());
+            Ok(Price {
+                date: match date {
+                    Some(date) => date,
+                    None => return Err(de::Error::missing_field("Date")),
+                },
+                adj_close: match adj_close {
+                    Some(adj_close) => adj_close,
+                    None => return Err(de::Error::missing_field("Adj. Close")),
+                },
+            })
+        }
+    }
+
+    #[test]
+    fn test_deserialize_rows() {
+        let csv = "Date,Open,Adj. Close\n2015-01-02,1.5,2.5\n2015-01-01,1.5,\n";
+        let dataset = RowIter::new(Cursor::new(csv)).unwrap().into_dataset().unwrap();
+        assert_eq!(dataset.deserialize_rows::<Price>().unwrap(),
+                   vec![Price {
+                            date: String::from("2015-01-02"),
+                            adj_close: Some(2.5),
+                        },
+                        Price {
+                            date: String::from("2015-01-01"),
+                            adj_close: None,
+                        }]);
+
+        // missing column
+        let csv = "Date,Open,Close\n2015-01-02,1.5,2.5\n";
+        let dataset = RowIter::new(Cursor::new(csv)).unwrap().into_dataset().unwrap();
+        let err = dataset.deserialize_rows::<Price>().unwrap_err().to_string();
+        assert!(err.contains("2015-01-02"), err);
+        assert!(err.contains("Date, Open, Close"), err);
+        assert!(err.contains("Adj. Close"), err);
+
+        // mistyped column
+        let err = dataset.deserialize_rows::<Vec<f64>>().unwrap_err().to_string();
+        assert!(err.contains("2015-01-02"), err);
+    }
+}
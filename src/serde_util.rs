@@ -0,0 +1,52 @@
+//! Helpers shared by the hand written serde implementations.
+
+use std::fmt::Display;
+use std::marker::PhantomData;
+use std::str::FromStr;
+use serde::de;
+
+/// Deserializes any type implementing `FromStr` from a string, reporting parse failures as
+/// syntax errors of the underlying format.
+pub struct FromStrVisitor<T> {
+    marker: PhantomData<T>,
+}
+
+impl<T> FromStrVisitor<T> {
+    pub fn new() -> FromStrVisitor<T> {
+        FromStrVisitor { marker: PhantomData }
+    }
+}
+
+impl<T> de::Visitor for FromStrVisitor<T>
+    where T: FromStr,
+          T::Err: Display
+{
+    type Value = T;
+
+    fn visit_str<E>(&mut self, value: &str) -> ::std::result::Result<T, E>
+        where E: de::Error
+    {
+        parse(value)
+    }
+}
+
+/// Parses a string field with `FromStr`.
+pub fn parse<T, E>(value: &str) -> ::std::result::Result<T, E>
+    where T: FromStr,
+          T::Err: Display,
+          E: de::Error
+{
+    value.parse().map_err(|err: T::Err| E::syntax(&err.to_string()))
+}
+
+/// Parses an optional string field, e.g. a date, with `FromStr`.
+pub fn parse_opt<T, E>(value: Option<String>) -> ::std::result::Result<Option<T>, E>
+    where T: FromStr,
+          T::Err: Display,
+          E: de::Error
+{
+    match value {
+        Some(s) => parse(&s).map(Some),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,387 @@
+use std::cmp;
+use std::collections::BTreeMap;
+use error::{Error, Result};
+use super::{NaiveDate, Dataset};
+
+/// A single column of a dataset indexed by date. Dates are unique and in ascending order,
+/// whatever the order of the response; missing values are `None`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TimeSeries {
+    dates: Vec<NaiveDate>,
+    values: Vec<Option<f64>>,
+}
+
+/// Which dates `TimeSeries::align` keeps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Join {
+    /// Dates of both series
+    Inner,
+    /// Dates of either series
+    Outer,
+    /// Dates of the first series
+    Left,
+}
+
+/// Statistic computed over each window by `TimeSeries::rolling`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rolling {
+    /// Arithmetic mean
+    Mean,
+    /// Sample standard deviation
+    Std,
+    /// Minimum
+    Min,
+    /// Maximum
+    Max,
+    /// Sum
+    Sum,
+}
+
+impl TimeSeries {
+    /// Creates a series from observations in any order. When a date appears more than once, the
+    /// last observation wins.
+    pub fn new<I>(observations: I) -> TimeSeries
+        where I: IntoIterator<Item = (NaiveDate, Option<f64>)>
+    {
+        let observations: BTreeMap<NaiveDate, Option<f64>> = observations.into_iter().collect();
+        TimeSeries {
+            dates: observations.keys().cloned().collect(),
+            values: observations.values().cloned().collect(),
+        }
+    }
+
+    /// The column called `column` of a dataset.
+    pub fn from_dataset(dataset: &Dataset, column: &str) -> Result<TimeSeries> {
+        let index = match dataset.value_index(column) {
+            Some(index) => index,
+            None => {
+                return Err(Error::Column(format!("unknown column `{}`, available columns: {}",
+                                                 column,
+                                                 dataset.column_names
+                                                        .iter()
+                                                        .skip(1)
+                                                        .cloned()
+                                                        .collect::<Vec<_>>()
+                                                        .join(", "))))
+            }
+        };
+        Ok(TimeSeries::new(dataset.rows
+                                  .iter()
+                                  .map(|row| (row.date, row.values.get(index).and_then(|v| *v)))))
+    }
+
+    /// Dates of the series in ascending order.
+    pub fn dates(&self) -> &[NaiveDate] {
+        &self.dates
+    }
+
+    /// Values of the series, in the order of `dates`.
+    pub fn values(&self) -> &[Option<f64>] {
+        &self.values
+    }
+
+    /// Number of observations.
+    pub fn len(&self) -> usize {
+        self.dates.len()
+    }
+
+    /// Whether the series has no observations.
+    pub fn is_empty(&self) -> bool {
+        self.dates.is_empty()
+    }
+
+    /// The value on `date`, `None` if the date is not in the series or its value is missing.
+    pub fn get(&self, date: NaiveDate) -> Option<f64> {
+        self.dates.binary_search(&date).ok().and_then(|i| self.values[i])
+    }
+
+    /// Iterates over the observations in ascending order.
+    pub fn iter<'a>(&'a self) -> Box<Iterator<Item = (NaiveDate, Option<f64>)> + 'a> {
+        Box::new(self.dates.iter().cloned().zip(self.values.iter().cloned()))
+    }
+
+    /// The observations from `start` to `end`, both included.
+    pub fn slice(&self, start: NaiveDate, end: NaiveDate) -> TimeSeries {
+        let from = match self.dates.binary_search(&start) {
+            Ok(i) | Err(i) => i,
+        };
+        let to = match self.dates.binary_search(&end) {
+            Ok(i) => i + 1,
+            Err(i) => i,
+        };
+        if from >= to {
+            return TimeSeries::default();
+        }
+        TimeSeries {
+            dates: self.dates[from..to].to_vec(),
+            values: self.values[from..to].to_vec(),
+        }
+    }
+
+    /// The series on `dates`, with `None` on dates that are not in the series.
+    pub fn reindex(&self, dates: &[NaiveDate]) -> TimeSeries {
+        TimeSeries::new(dates.iter().map(|&date| (date, self.get(date))))
+    }
+
+    /// Reindex both series on the same dates, chosen with `join`.
+    pub fn align(&self, other: &TimeSeries, join: Join) -> (TimeSeries, TimeSeries) {
+        let dates: Vec<NaiveDate> = match join {
+            Join::Left => self.dates.clone(),
+            Join::Inner => {
+                self.dates
+                    .iter()
+                    .cloned()
+                    .filter(|date| other.dates.binary_search(date).is_ok())
+                    .collect()
+            }
+            Join::Outer => {
+                let mut dates = self.dates.clone();
+                dates.extend(other.dates.iter().cloned());
+                dates.sort();
+                dates.dedup();
+                dates
+            }
+        };
+        (self.reindex(&dates), other.reindex(&dates))
+    }
+
+    /// Shift the values by `periods` observations, keeping the dates. With a positive `periods`
+    /// each date gets an earlier value (a lag); with a negative one, a later value. Dates without
+    /// a shifted value get `None`.
+    pub fn shift(&self, periods: i64) -> TimeSeries {
+        let len = self.len() as i64;
+        // shifting by more than the length gives the same result, without overflowing `i - periods`
+        let periods = cmp::max(cmp::min(periods, len), -len);
+        let values = (0..len)
+                         .map(|i| {
+                             let source = i - periods;
+                             if source >= 0 && source < len {
+                                 self.values[source as usize]
+                             } else {
+                                 None
+                             }
+                         })
+                         .collect();
+        TimeSeries {
+            dates: self.dates.clone(),
+            values: values,
+        }
+    }
+
+    /// Compute `statistic` over each window of `window` observations ending at each date. The
+    /// value is `None` until a full window is available and whenever the window contains a
+    /// missing value.
+    pub fn rolling(&self, window: usize, statistic: Rolling) -> TimeSeries {
+        let values = (0..self.len())
+                         .map(|i| {
+                             if window == 0 || i + 1 < window {
+                                 return None;
+                             }
+                             let mut values = Vec::with_capacity(window);
+                             for value in &self.values[i + 1 - window..i + 1] {
+                                 match *value {
+                                     Some(value) => values.push(value),
+                                     None => return None,
+                                 }
+                             }
+                             rolling_statistic(&values, statistic)
+                         })
+                         .collect();
+        TimeSeries {
+            dates: self.dates.clone(),
+            values: values,
+        }
+    }
+
+    /// Rolling mean, see `rolling`.
+    pub fn rolling_mean(&self, window: usize) -> TimeSeries {
+        self.rolling(window, Rolling::Mean)
+    }
+
+    /// Rolling sample standard deviation, see `rolling`.
+    pub fn rolling_std(&self, window: usize) -> TimeSeries {
+        self.rolling(window, Rolling::Std)
+    }
+
+    /// Rolling minimum, see `rolling`.
+    pub fn rolling_min(&self, window: usize) -> TimeSeries {
+        self.rolling(window, Rolling::Min)
+    }
+
+    /// Rolling maximum, see `rolling`.
+    pub fn rolling_max(&self, window: usize) -> TimeSeries {
+        self.rolling(window, Rolling::Max)
+    }
+
+    /// Rolling sum, see `rolling`.
+    pub fn rolling_sum(&self, window: usize) -> TimeSeries {
+        self.rolling(window, Rolling::Sum)
+    }
+
+    /// Replace missing values with the last earlier value. Leading missing values stay missing.
+    pub fn ffill(&self) -> TimeSeries {
+        let mut last = None;
+        let values = self.values
+                         .iter()
+                         .map(|value| {
+                             if value.is_some() {
+                                 last = *value;
+                             }
+                             last
+                         })
+                         .collect();
+        TimeSeries {
+            dates: self.dates.clone(),
+            values: values,
+        }
+    }
+
+    /// Replace missing values with the next later value. Trailing missing values stay missing.
+    pub fn bfill(&self) -> TimeSeries {
+        let mut next = None;
+        let mut values: Vec<Option<f64>> = self.values
+                                               .iter()
+                                               .rev()
+                                               .map(|value| {
+                                                   if value.is_some() {
+                                                       next = *value;
+                                                   }
+                                                   next
+                                               })
+                                               .collect();
+        values.reverse();
+        TimeSeries {
+            dates: self.dates.clone(),
+            values: values,
+        }
+    }
+}
+
+/// `statistic` of a full window without missing values.
+fn rolling_statistic(values: &[f64], statistic: Rolling) -> Option<f64> {
+    let n = values.len() as f64;
+    let sum = values.iter().fold(0.0, |sum, v| sum + v);
+    match statistic {
+        Rolling::Sum => Some(sum),
+        Rolling::Mean => Some(sum / n),
+        Rolling::Std => {
+            if values.len() < 2 {
+                return None;
+            }
+            let mean = sum / n;
+            let squares = values.iter().fold(0.0, |acc, v| acc + (v - mean) * (v - mean));
+            Some((squares / (n - 1.0)).sqrt())
+        }
+        Rolling::Min => Some(values.iter().cloned().fold(::std::f64::INFINITY, f64::min)),
+        Rolling::Max => Some(values.iter().cloned().fold(::std::f64::NEG_INFINITY, f64::max)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{Dataset, NaiveDate, Row};
+
+    fn ymd(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd(2015, 1, d)
+    }
+
+    fn series() -> TimeSeries {
+        TimeSeries::new(vec![(ymd(6), Some(4.0)),
+                             (ymd(2), Some(1.0)),
+                             (ymd(5), None),
+                             (ymd(7), Some(8.0)),
+                             (ymd(8), Some(16.0)),
+                             (ymd(9), Some(2.0))])
+    }
+
+    #[test]
+    fn test_new_and_from_dataset() {
+        let series = series();
+        assert_eq!(series.dates(), &[ymd(2), ymd(5), ymd(6), ymd(7), ymd(8), ymd(9)]);
+        assert_eq!(series.get(ymd(6)), Some(4.0));
+        assert_eq!(series.get(ymd(5)), None);
+        assert_eq!(series.get(ymd(3)), None);
+        assert_eq!(TimeSeries::new(vec![(ymd(2), Some(1.0)), (ymd(2), Some(2.0))]).values(),
+                   &[Some(2.0)]);
+
+        let dataset = Dataset {
+            column_names: vec![String::from("Date"), String::from("Open"), String::from("Close")],
+            rows: vec![Row::new(ymd(6), vec![Some(1.5), Some(2.5)]),
+                       Row::new(ymd(5), vec![Some(0.5), None])],
+        };
+        let close = TimeSeries::from_dataset(&dataset, "Close").unwrap();
+        assert_eq!(close.iter().collect::<Vec<_>>(),
+                   vec![(ymd(5), None), (ymd(6), Some(2.5))]);
+        assert!(TimeSeries::from_dataset(&dataset, "Volume").is_err());
+    }
+
+    #[test]
+    fn test_slice() {
+        let series = series();
+        assert_eq!(series.slice(ymd(3), ymd(7)).dates(), &[ymd(5), ymd(6), ymd(7)]);
+        assert_eq!(series.slice(ymd(2), ymd(2)).values(), &[Some(1.0)]);
+        assert!(series.slice(ymd(10), ymd(20)).is_empty());
+        assert!(series.slice(ymd(7), ymd(6)).is_empty());
+    }
+
+    #[test]
+    fn test_align() {
+        let left = series();
+        let right = TimeSeries::new(vec![(ymd(1), Some(0.5)), (ymd(6), Some(3.0))]);
+
+        let (a, b) = left.align(&right, Join::Inner);
+        assert_eq!(a.iter().collect::<Vec<_>>(), vec![(ymd(6), Some(4.0))]);
+        assert_eq!(b.iter().collect::<Vec<_>>(), vec![(ymd(6), Some(3.0))]);
+
+        let (a, b) = left.align(&right, Join::Outer);
+        assert_eq!(a.len(), 7);
+        assert_eq!(a.values()[0], None);
+        assert_eq!(b.values()[0], Some(0.5));
+
+        let (a, b) = left.align(&right, Join::Left);
+        assert_eq!(a, left);
+        assert_eq!(b.values(), &[None, None, Some(3.0), None, None, None]);
+    }
+
+    #[test]
+    fn test_shift() {
+        let series = series();
+        assert_eq!(series.shift(1).values(),
+                   &[None, Some(1.0), None, Some(4.0), Some(8.0), Some(16.0)]);
+        assert_eq!(series.shift(-2).values(),
+                   &[Some(4.0), Some(8.0), Some(16.0), Some(2.0), None, None]);
+        assert_eq!(series.shift(0), series);
+        assert_eq!(series.shift(10).values(), &[None; 6]);
+        assert_eq!(series.shift(::std::i64::MAX).values(), &[None; 6]);
+        assert_eq!(series.shift(::std::i64::MIN).values(), &[None; 6]);
+        assert_eq!(series.shift(-6).values(), &[None; 6]);
+    }
+
+    #[test]
+    fn test_rolling() {
+        let series = series();
+        assert_eq!(series.rolling_sum(2).values(),
+                   &[None, None, None, Some(12.0), Some(24.0), Some(18.0)]);
+        assert_eq!(series.rolling_mean(3).values(),
+                   &[None, None, None, None, Some(28.0 / 3.0), Some(26.0 / 3.0)]);
+        assert_eq!(series.rolling_min(2).values()[5], Some(2.0));
+        assert_eq!(series.rolling_max(2).values()[5], Some(16.0));
+        assert_eq!(series.rolling_std(2).values()[3], Some(8.0f64.sqrt()));
+        assert_eq!(series.rolling_std(1).values()[0], None);
+        assert_eq!(series.rolling_mean(0).values(), &[None; 6]);
+    }
+
+    #[test]
+    fn test_fill() {
+        let series = TimeSeries::new(vec![(ymd(1), None),
+                                          (ymd(2), Some(1.0)),
+                                          (ymd(3), None),
+                                          (ymd(4), Some(3.0)),
+                                          (ymd(5), None)]);
+        assert_eq!(series.ffill().values(),
+                   &[None, Some(1.0), Some(1.0), Some(3.0), Some(3.0)]);
+        assert_eq!(series.bfill().values(),
+                   &[Some(1.0), Some(1.0), Some(3.0), Some(3.0), None]);
+    }
+}
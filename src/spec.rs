@@ -0,0 +1,279 @@
+use serde::{ser, de, Serialize, Serializer, Deserialize, Deserializer};
+use serde_util;
+use super::{NaiveDate, Quandl, QuandlRequest, Order, Collapse, Transform, Chunking};
+
+/// The parameters of a `QuandlRequest` without the `Quandl` client. A `RequestSpec` can be
+/// serialized with serde (e.g. to JSON or TOML), stored, and later executed with
+/// `Quandl::execute` to replay exactly the same query.
+///
+/// Dates are serialized as `yyyy-mm-dd` strings and `Order`, `Collapse` and `Transform` use
+/// the lowercase spelling of the Quandl API. Unset parameters are serialized as `null`, or left
+/// out in formats without null like TOML, and may be omitted when deserializing.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RequestSpec {
+    /// The unique database code on Quandl (ex. WIKI)
+    pub database_code: String,
+    /// The unique dataset code on Quandl (ex. APPL)
+    pub dataset_code: String,
+    /// Only get the first `n` rows of the dataset.
+    pub limit: Option<u64>,
+    /// Only get the first `n` rows of the dataset.
+    pub rows: Option<u64>,
+    /// Request specific column.
+    pub column_index: Option<u64>,
+    /// Request columns by name.
+    pub columns: Vec<String>,
+    /// Start of the requested date range.
+    pub start_date: Option<NaiveDate>,
+    /// End of the requested date range.
+    pub end_date: Option<NaiveDate>,
+    /// Sort in ascending or descending order.
+    pub order: Option<Order>,
+    /// Parameters to indicate the desired frequency.
+    pub collapse: Option<Collapse>,
+    /// Perform calculations on your data prior to downloading.
+    pub transform: Option<Transform>,
+    /// Split the date range into windows that are fetched separately.
+    pub chunking: Option<Chunking>,
+}
+
+impl RequestSpec {
+    /// Creates a new `RequestSpec` using the specified database_code and dataset code. All
+    /// optional parameters are set to `None`.
+    pub fn new(database_code: &str, dataset_code: &str) -> RequestSpec {
+        RequestSpec {
+            database_code: String::from(database_code),
+            dataset_code: String::from(dataset_code),
+            ..RequestSpec::default()
+        }
+    }
+
+    /// Creates a `QuandlRequest` with these parameters that will be sent using `quandl`.
+    pub fn to_request<'a>(&self, quandl: &'a Quandl) -> QuandlRequest<'a> {
+        QuandlRequest {
+            database_code: self.database_code.clone(),
+            dataset_code: self.dataset_code.clone(),
+            limit: self.limit,
+            rows: self.rows,
+            column_index: self.column_index,
+            columns: self.columns.clone(),
+            start_date: self.start_date,
+            end_date: self.end_date,
+            order: self.order,
+            collapse: self.collapse,
+            transform: self.transform,
+            chunking: self.chunking,
+            ..QuandlRequest::default(quandl)
+        }
+    }
+}
+
+const FIELDS: &'static [&'static str] = &["database_code",
+                                          "dataset_code",
+                                          "limit",
+                                          "rows",
+                                          "column_index",
+                                          "columns",
+                                          "start_date",
+                                          "end_date",
+                                          "order",
+                                          "collapse",
+                                          "transform",
+                                          "chunking"];
+
+impl Serialize for RequestSpec {
+    fn serialize<S>(&self, serializer: &mut S) -> Result<(), S::Error>
+        where S: Serializer
+    {
+        serializer.visit_struct("RequestSpec",
+                                SpecMapVisitor {
+                                    spec: self,
+                                    state: 0,
+                                })
+    }
+}
+
+/// Serializes the fields of a `RequestSpec` one at a time, in the order of `FIELDS`.
+struct SpecMapVisitor<'a> {
+    spec: &'a RequestSpec,
+    state: usize,
+}
+
+impl<'a> ser::MapVisitor for SpecMapVisitor<'a> {
+    fn visit<S>(&mut self, serializer: &mut S) -> Result<Option<()>, S::Error>
+        where S: Serializer
+    {
+        let spec = self.spec;
+        let key = match FIELDS.get(self.state) {
+            Some(key) => *key,
+            None => return Ok(None),
+        };
+        self.state += 1;
+
+        try!(match key {
+            "database_code" => serializer.visit_struct_elt(key, &spec.database_code),
+            "dataset_code" => serializer.visit_struct_elt(key, &spec.dataset_code),
+            "limit" => serializer.visit_struct_elt(key, &spec.limit),
+            "rows" => serializer.visit_struct_elt(key, &spec.rows),
+            "column_index" => serializer.visit_struct_elt(key, &spec.column_index),
+            "columns" => serializer.visit_struct_elt(key, &spec.columns),
+            "start_date" => {
+                serializer.visit_struct_elt(key, &spec.start_date.map(|d| d.to_string()))
+            }
+            "end_date" => serializer.visit_struct_elt(key, &spec.end_date.map(|d| d.to_string())),
+            "order" => serializer.visit_struct_elt(key, &spec.order),
+            "collapse" => serializer.visit_struct_elt(key, &spec.collapse),
+            "transform" => serializer.visit_struct_elt(key, &spec.transform),
+            _ => serializer.visit_struct_elt(key, &spec.chunking),
+        });
+        Ok(Some(()))
+    }
+
+    fn len(&self) -> Option<usize> {
+        Some(FIELDS.len())
+    }
+}
+
+impl Deserialize for RequestSpec {
+    fn deserialize<D>(deserializer: &mut D) -> Result<RequestSpec, D::Error>
+        where D: Deserializer
+    {
+        deserializer.visit_struct("RequestSpec", FIELDS, SpecVisitor)
+    }
+}
+
+struct SpecVisitor;
+
+impl de::Visitor for SpecVisitor {
+    type Value = RequestSpec;
+
+    fn visit_map<V>(&mut self, mut visitor: V) -> Result<RequestSpec, V::Error>
+        where V: de::MapVisitor
+    {
+        let mut database_code: Option<String> = None;
+        let mut dataset_code: Option<String> = None;
+        let mut spec = RequestSpec::default();
+
+        while let Some(key) = try!(visitor.visit_key::<String>()) {
+            match &key[..] {
+                "database_code" => database_code = Some(try!(visitor.visit_value())),
+                "dataset_code" => dataset_code = Some(try!(visitor.visit_value())),
+                "limit" => spec.limit = try!(visitor.visit_value()),
+                "rows" => spec.rows = try!(visitor.visit_value()),
+                "column_index" => spec.column_index = try!(visitor.visit_value()),
+                "columns" => {
+                    let columns: Option<Vec<String>> = try!(visitor.visit_value());
+                    spec.columns = columns.unwrap_or_else(Vec::new);
+                }
+                "start_date" => {
+                    let date = try!(visitor.visit_value());
+                    spec.start_date = try!(serde_util::parse_opt::<_, V::Error>(date))
+                }
+                "end_date" => {
+                    let date = try!(visitor.visit_value());
+                    spec.end_date = try!(serde_util::parse_opt::<_, V::Error>(date))
+                }
+                "order" => spec.order = try!(visitor.visit_value()),
+                "collapse" => spec.collapse = try!(visitor.visit_value()),
+                "transform" => spec.transform = try!(visitor.visit_value()),
+                "chunking" => spec.chunking = try!(visitor.visit_value()),
+                field => return Err(de::Error::unknown_field(field)),
+            }
+        }
+        try!(visitor.end());
+
+        spec.database_code = match database_code {
+            Some(code) => code,
+            None => return Err(de::Error::missing_field("database_code")),
+        };
+        spec.dataset_code = match dataset_code {
+            Some(code) => code,
+            None => return Err(de::Error::missing_field("dataset_code")),
+        };
+        Ok(spec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use toml;
+    use super::*;
+    use super::super::{NaiveDate, Quandl, Order, Collapse, Transform};
+
+    fn full_spec() -> RequestSpec {
+        RequestSpec {
+            limit: Some(10),
+            column_index: Some(4),
+            start_date: Some(NaiveDate::from_ymd(2015, 2, 10)),
+            end_date: Some(NaiveDate::from_ymd(2015, 3, 10)),
+            order: Some(Order::Asc),
+            collapse: Some(Collapse::Monthly),
+            transform: Some(Transform::Rdiff),
+            ..RequestSpec::new("WIKI", "AAPL")
+        }
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let spec = full_spec();
+        let json = serde_json::to_string(&spec).unwrap();
+        assert_eq!(json,
+                   "{\"database_code\":\"WIKI\",\"dataset_code\":\"AAPL\",\"limit\":10,\
+                    \"rows\":null,\"column_index\":4,\"columns\":[],\"start_date\":\"2015-02-10\",\
+                    \"end_date\":\"2015-03-10\",\"order\":\"asc\",\"collapse\":\"monthly\",\
+                    \"transform\":\"rdiff\",\"chunking\":null}");
+        let back: RequestSpec = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, spec);
+    }
+
+    #[test]
+    fn test_toml_round_trip() {
+        let mut spec = full_spec();
+        spec.columns = vec![String::from("Close"), String::from("Volume")];
+        let encoded = toml::encode_str(&spec);
+        assert!(encoded.contains("start_date = \"2015-02-10\""), encoded);
+        assert!(encoded.contains("collapse = \"monthly\""), encoded);
+        // TOML has no null, unset parameters are left out
+        assert!(!encoded.contains("rows"), encoded);
+        let back: RequestSpec = toml::decode_str(&encoded).unwrap();
+        assert_eq!(back, spec);
+    }
+
+    #[test]
+    fn test_deserialize_omitted_fields() {
+        let spec: RequestSpec = serde_json::from_str("{\"database_code\":\"WIKI\",\
+                                                      \"dataset_code\":\"AAPL\"}")
+                                    .unwrap();
+        assert_eq!(spec, RequestSpec::new("WIKI", "AAPL"));
+    }
+
+    #[test]
+    fn test_deserialize_errors() {
+        // missing dataset code
+        let res = serde_json::from_str::<RequestSpec>("{\"database_code\":\"WIKI\"}");
+        assert!(res.is_err());
+
+        // invalid order
+        let res = serde_json::from_str::<RequestSpec>("{\"database_code\":\"WIKI\",\
+                                                       \"dataset_code\":\"AAPL\",\
+                                                       \"order\":\"sideways\"}");
+        assert!(res.is_err());
+
+        // invalid date
+        let res = serde_json::from_str::<RequestSpec>("{\"database_code\":\"WIKI\",\
+                                                       \"dataset_code\":\"AAPL\",\
+                                                       \"start_date\":\"2015-13-01\"}");
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_request_round_trip() {
+        let q = Quandl::new();
+        let spec = full_spec();
+        let req = spec.to_request(&q);
+        assert_eq!(req.database_code, String::from("WIKI"));
+        assert_eq!(req.collapse, Some(Collapse::Monthly));
+        assert_eq!(req.spec(), spec);
+    }
+}
@@ -0,0 +1,322 @@
+use chrono::Duration;
+use series::Join;
+use super::{NaiveDate, Collapse, TimeSeries};
+
+/// Number of periods of `collapse` in a year: 252 trading days, 52 weeks, 12 months, 4 quarters
+/// or 1 year. `Collapse::None` is treated as daily data.
+pub fn periods_per_year(collapse: Collapse) -> f64 {
+    match collapse {
+        Collapse::None | Collapse::Daily => 252.0,
+        Collapse::Weekly => 52.0,
+        Collapse::Monthly => 12.0,
+        Collapse::Quarterly => 4.0,
+        Collapse::Annual => 1.0,
+    }
+}
+
+/// Compound annual growth rate of a series of simple returns, like those of
+/// `Transform::Rdiff`, at the frequency of `collapse`. Missing values are skipped. `None` if
+/// there are no returns.
+pub fn annualized_return(returns: &TimeSeries, collapse: Collapse) -> Option<f64> {
+    let returns = present(returns);
+    if returns.is_empty() {
+        return None;
+    }
+    let growth = returns.iter().fold(1.0, |growth, r| growth * (1.0 + r));
+    Some(growth.powf(periods_per_year(collapse) / returns.len() as f64) - 1.0)
+}
+
+/// Sample standard deviation of the returns scaled to a year with the square root of
+/// `periods_per_year`. `None` with fewer than two returns.
+pub fn annualized_volatility(returns: &TimeSeries, collapse: Collapse) -> Option<f64> {
+    std(&present(returns)).map(|std| std * periods_per_year(collapse).sqrt())
+}
+
+/// Annualized Sharpe ratio: the mean excess return over the per period `risk_free` rate,
+/// divided by the standard deviation of the returns. `risk_free` is an annual rate.
+pub fn sharpe_ratio(returns: &TimeSeries, risk_free: f64, collapse: Collapse) -> Option<f64> {
+    let periods = periods_per_year(collapse);
+    let returns = present(returns);
+    match (mean(&returns), std(&returns)) {
+        (Some(mean), Some(std)) if std > 0.0 => {
+            Some((mean - risk_free / periods) / std * periods.sqrt())
+        }
+        _ => None,
+    }
+}
+
+/// Annualized Sortino ratio: like `sharpe_ratio`, but divided by the downside deviation, the
+/// root mean square of the excess returns below zero over all periods.
+pub fn sortino_ratio(returns: &TimeSeries, risk_free: f64, collapse: Collapse) -> Option<f64> {
+    let periods = periods_per_year(collapse);
+    let target = risk_free / periods;
+    let returns = present(returns);
+    let mean = match mean(&returns) {
+        Some(mean) => mean,
+        None => return None,
+    };
+    let downside = returns.iter()
+                          .map(|r| (r - target).min(0.0))
+                          .fold(0.0, |sum, d| sum + d * d) / returns.len() as f64;
+    if downside > 0.0 {
+        Some((mean - target) / downside.sqrt() * periods.sqrt())
+    } else {
+        None
+    }
+}
+
+/// The largest peak to trough decline of a series.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Drawdown {
+    /// Decline from the peak as a fraction, e.g. `0.25` for a 25% loss
+    pub depth: f64,
+    /// Date of the peak
+    pub peak: NaiveDate,
+    /// Date of the trough
+    pub trough: NaiveDate,
+    /// First date the peak was reached again, `None` if it wasn't
+    pub recovery: Option<NaiveDate>,
+    /// Time from the peak to the recovery, or to the end of the series if there was no
+    /// recovery
+    pub duration: Duration,
+}
+
+/// The maximum drawdown of the value of an investment earning `returns`, starting with the
+/// first date of the series. The value starts at 1 before the first return, so a loss on the
+/// first date is a drawdown from a peak on that date. `None` if the value never declines.
+pub fn max_drawdown(returns: &TimeSeries) -> Option<Drawdown> {
+    let mut value = 1.0;
+    let mut peak: Option<(NaiveDate, f64)> = returns.dates().first().map(|&date| (date, 1.0));
+    // the deepest drawdown so far and the value at its peak
+    let mut worst: Option<(Drawdown, f64)> = None;
+    for (date, r) in returns.iter() {
+        value *= 1.0 + r.unwrap_or(0.0);
+        match peak {
+            Some((peak_date, peak_value)) if value < peak_value => {
+                let depth = 1.0 - value / peak_value;
+                if worst.as_ref().map_or(true, |&(ref worst, _)| depth > worst.depth) {
+                    let drawdown = Drawdown {
+                        depth: depth,
+                        peak: peak_date,
+                        trough: date,
+                        recovery: None,
+                        duration: Duration::zero(),
+                    };
+                    worst = Some((drawdown, peak_value));
+                }
+            }
+            _ => {
+                if let Some((ref mut worst, worst_peak)) = worst {
+                    if worst.recovery.is_none() && value >= worst_peak {
+                        worst.recovery = Some(date);
+                    }
+                }
+                peak = Some((date, value));
+            }
+        }
+    }
+    let end = returns.dates().last().cloned();
+    worst.map(|(mut worst, _)| {
+        if let Some(end) = worst.recovery.or(end) {
+            worst.duration = end - worst.peak;
+        }
+        worst
+    })
+}
+
+/// Beta of `returns` against `benchmark` returns: their covariance divided by the variance of
+/// the benchmark, over the dates where both have a value.
+pub fn beta(returns: &TimeSeries, benchmark: &TimeSeries) -> Option<f64> {
+    let (a, b) = paired(returns, benchmark);
+    match (covariance(&a, &b), covariance(&b, &b)) {
+        (Some(cov), Some(var)) if var > 0.0 => Some(cov / var),
+        _ => None,
+    }
+}
+
+/// Pearson correlation of two series over the dates where both have a value.
+pub fn correlation(a: &TimeSeries, b: &TimeSeries) -> Option<f64> {
+    let (a, b) = paired(a, b);
+    match (covariance(&a, &b), std(&a), std(&b)) {
+        (Some(cov), Some(std_a), Some(std_b)) if std_a > 0.0 && std_b > 0.0 => {
+            Some(cov / (std_a * std_b))
+        }
+        _ => None,
+    }
+}
+
+/// Sample covariance matrix of several series, over the dates where all of them have a value.
+/// `None` with fewer than two such dates.
+pub fn covariance_matrix(series: &[TimeSeries]) -> Option<Vec<Vec<f64>>> {
+    let first = match series.first() {
+        Some(first) => first,
+        None => return Some(Vec::new()),
+    };
+    let dates: Vec<NaiveDate> = first.iter()
+                                     .filter(|&(date, value)| {
+                                         value.is_some() &&
+                                         series.iter().all(|s| s.get(date).is_some())
+                                     })
+                                     .map(|(date, _)| date)
+                                     .collect();
+    if dates.len() < 2 {
+        return None;
+    }
+    let columns: Vec<Vec<f64>> = series.iter()
+                                       .map(|s| dates.iter().filter_map(|&d| s.get(d)).collect())
+                                       .collect();
+    let mut matrix = vec![vec![0.0; series.len()]; series.len()];
+    for i in 0..columns.len() {
+        for j in i..columns.len() {
+            let cov = covariance(&columns[i], &columns[j]).unwrap_or(0.0);
+            matrix[i][j] = cov;
+            matrix[j][i] = cov;
+        }
+    }
+    Some(matrix)
+}
+
+/// The non-missing values of a series.
+fn present(series: &TimeSeries) -> Vec<f64> {
+    series.values().iter().filter_map(|v| *v).collect()
+}
+
+/// The values of both series on the dates where both have a value.
+fn paired(a: &TimeSeries, b: &TimeSeries) -> (Vec<f64>, Vec<f64>) {
+    let (a, b) = a.align(b, Join::Inner);
+    a.values()
+     .iter()
+     .zip(b.values().iter())
+     .filter_map(|(a, b)| {
+         match (*a, *b) {
+             (Some(a), Some(b)) => Some((a, b)),
+             _ => None,
+         }
+     })
+     .unzip()
+}
+
+fn mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().fold(0.0, |sum, v| sum + v) / values.len() as f64)
+    }
+}
+
+fn std(values: &[f64]) -> Option<f64> {
+    covariance(values, values).map(f64::sqrt)
+}
+
+/// Sample covariance, `None` with fewer than two values.
+fn covariance(a: &[f64], b: &[f64]) -> Option<f64> {
+    if a.len() < 2 || a.len() != b.len() {
+        return None;
+    }
+    let (mean_a, mean_b) = (mean(a).unwrap_or(0.0), mean(b).unwrap_or(0.0));
+    let sum = a.iter().zip(b.iter()).fold(0.0, |sum, (a, b)| sum + (a - mean_a) * (b - mean_b));
+    Some(sum / (a.len() - 1) as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use super::*;
+    use super::super::{Collapse, NaiveDate, TimeSeries};
+
+    fn ymd(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd(2015, 1, d)
+    }
+
+    fn series(values: &[Option<f64>]) -> TimeSeries {
+        TimeSeries::new(values.iter().enumerate().map(|(i, v)| (ymd(i as u32 + 1), *v)))
+    }
+
+    fn assert_close(actual: Option<f64>, expected: f64) {
+        let actual = actual.unwrap();
+        assert!((actual - expected).abs() < 1e-12, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn test_annualized() {
+        // four quarters of 10% growth
+        let returns = series(&[Some(0.1), Some(0.1), None, Some(0.1), Some(0.1)]);
+        assert_close(annualized_return(&returns, Collapse::Quarterly), 1.1f64.powi(4) - 1.0);
+        assert_close(annualized_return(&returns, Collapse::Annual), 1.1 - 1.0);
+        assert_close(annualized_volatility(&returns, Collapse::Monthly), 0.0);
+        assert_eq!(annualized_return(&series(&[]), Collapse::Daily), None);
+
+        let returns = series(&[Some(0.01), Some(-0.01)]);
+        assert_close(annualized_volatility(&returns, Collapse::Daily),
+                     0.0002f64.sqrt() * 252f64.sqrt());
+    }
+
+    #[test]
+    fn test_ratios() {
+        let returns = series(&[Some(0.02), Some(-0.01), Some(0.03), Some(0.0)]);
+        let mean = 0.01;
+        let std = (0.0001f64 + 0.0004 + 0.0004 + 0.0001) / 3.0;
+        assert_close(sharpe_ratio(&returns, 0.0, Collapse::Monthly),
+                     mean / std.sqrt() * 12f64.sqrt());
+        assert_close(sortino_ratio(&returns, 0.0, Collapse::Monthly),
+                     mean / (0.0001f64 / 4.0).sqrt() * 12f64.sqrt());
+        assert_eq!(sortino_ratio(&series(&[Some(0.1), Some(0.2)]), 0.0, Collapse::Daily),
+                   None);
+        assert_eq!(sharpe_ratio(&series(&[Some(0.1)]), 0.0, Collapse::Daily), None);
+    }
+
+    #[test]
+    fn test_max_drawdown() {
+        // value: 1.0, 2.0, 1.0, 1.5, 0.75, 1.5, 2.25
+        let returns = series(&[Some(0.0), Some(1.0), Some(-0.5), Some(0.5), Some(-0.5),
+                               Some(1.0), Some(0.5)]);
+        let drawdown = max_drawdown(&returns).unwrap();
+        assert_eq!(drawdown.depth, 0.625);
+        assert_eq!(drawdown.peak, ymd(2));
+        assert_eq!(drawdown.trough, ymd(5));
+        assert_eq!(drawdown.recovery, Some(ymd(7)));
+        assert_eq!(drawdown.duration, Duration::days(5));
+
+        let returns = series(&[Some(0.0), Some(-0.5), Some(0.5)]);
+        let drawdown = max_drawdown(&returns).unwrap();
+        assert_eq!(drawdown.recovery, None);
+        assert_eq!(drawdown.duration, Duration::days(2));
+
+        assert_eq!(max_drawdown(&series(&[Some(0.1), Some(0.1)])), None);
+        assert_eq!(max_drawdown(&TimeSeries::default()), None);
+
+        // losses from the first return are measured from the initial value
+        let drawdown = max_drawdown(&series(&[Some(-0.1), Some(-0.1)])).unwrap();
+        assert_close(Some(drawdown.depth), 0.19);
+        assert_eq!(drawdown.peak, ymd(1));
+        assert_eq!(drawdown.trough, ymd(2));
+        assert_eq!(drawdown.recovery, None);
+
+        let drawdown = max_drawdown(&series(&[Some(-0.5)])).unwrap();
+        assert_eq!(drawdown.depth, 0.5);
+        assert_eq!(drawdown.trough, ymd(1));
+        assert_eq!(drawdown.duration, Duration::days(0));
+    }
+
+    #[test]
+    fn test_beta_and_correlation() {
+        let benchmark = series(&[Some(0.01), Some(-0.02), Some(0.03), None, Some(0.01)]);
+        let returns = series(&[Some(0.02), Some(-0.04), Some(0.06), Some(0.5), Some(0.02)]);
+        assert_close(beta(&returns, &benchmark), 2.0);
+        assert_close(correlation(&returns, &benchmark), 1.0);
+
+        let inverse = series(&[Some(-0.01), Some(0.02), Some(-0.03), Some(0.0), Some(-0.01)]);
+        assert_close(correlation(&inverse, &benchmark), -1.0);
+        assert_eq!(beta(&returns, &series(&[Some(0.1), Some(0.1)])), None);
+    }
+
+    #[test]
+    fn test_covariance_matrix() {
+        let a = series(&[Some(1.0), Some(2.0), Some(3.0), None]);
+        let b = series(&[Some(2.0), Some(4.0), Some(6.0), Some(8.0)]);
+        let matrix = covariance_matrix(&[a.clone(), b]).unwrap();
+        assert_eq!(matrix, vec![vec![1.0, 2.0], vec![2.0, 4.0]]);
+        assert_eq!(covariance_matrix(&[]), Some(Vec::new()));
+        assert_eq!(covariance_matrix(&[a.slice(ymd(1), ymd(1))]), None);
+    }
+}
@@ -0,0 +1,401 @@
+use super::{Row, Transform};
+
+/// Apply `transform` locally with the same semantics as the Quandl API, e.g. to data loaded from
+/// a cache or to verify data transformed by Quandl. See the functions of this module for the
+/// definition of each transform.
+pub fn apply(transform: Transform, rows: &[Row]) -> Vec<Row> {
+    match transform {
+        Transform::None => rows.to_vec(),
+        Transform::Diff => diff(rows),
+        Transform::Rdiff => rdiff(rows),
+        Transform::RdiffFrom => rdiff_from(rows),
+        Transform::Cumul => cumul(rows),
+        Transform::Normalize => normalize(rows),
+    }
+}
+
+/// Row on row change: `z[t] = y[t] - y[t-1]`. The oldest row is dropped.
+pub fn diff(rows: &[Row]) -> Vec<Row> {
+    pairwise(rows, |prev, cur| Some(cur - prev))
+}
+
+/// Percentage change: `z[t] = (y[t] - y[t-1]) / y[t-1]`. The oldest row is dropped.
+pub fn rdiff(rows: &[Row]) -> Vec<Row> {
+    pairwise(rows, |prev, cur| ratio(cur - prev, prev))
+}
+
+/// Logarithmic return: `z[t] = ln(y[t] / y[t-1])`. The oldest row is dropped.
+pub fn log_returns(rows: &[Row]) -> Vec<Row> {
+    pairwise(rows, |prev, cur| {
+        ratio(cur, prev).and_then(|r| if r > 0.0 { Some(r.ln()) } else { None })
+    })
+}
+
+/// Cumulative sum: `z[t] = y[0] + y[1] + ... + y[t]`, starting from the oldest row. Missing
+/// values stay missing and don't change the sum.
+pub fn cumul(rows: &[Row]) -> Vec<Row> {
+    let width = width(rows);
+    let mut sums = vec![0.0; width];
+    map_chronological(rows, |_, row| {
+        row.values
+           .iter()
+           .zip(sums.iter_mut())
+           .map(|(value, sum)| {
+               value.map(|value| {
+                   *sum += value;
+                   *sum
+               })
+           })
+           .collect()
+    })
+}
+
+/// Normalize to 100 at the oldest value: `z[t] = y[t] / y[0] * 100`. The base of each column is
+/// its oldest non-missing value.
+pub fn normalize(rows: &[Row]) -> Vec<Row> {
+    let bases = first_values(chronological(rows).into_iter(), width(rows));
+    map_values(rows, |i, value| bases[i].and_then(|base| ratio(value * 100.0, base)))
+}
+
+/// Percentage change from each row to the latest value: `z[t] = (y[n] - y[t]) / y[t]`. The
+/// base of each column is its latest non-missing value.
+pub fn rdiff_from(rows: &[Row]) -> Vec<Row> {
+    let bases = first_values(chronological(rows).into_iter().rev(), width(rows));
+    map_values(rows, |i, value| bases[i].and_then(|base| ratio(base - value, value)))
+}
+
+/// `numerator / denominator`, or `None` when dividing by zero.
+fn ratio(numerator: f64, denominator: f64) -> Option<f64> {
+    if denominator == 0.0 {
+        None
+    } else {
+        Some(numerator / denominator)
+    }
+}
+
+/// Number of values per row.
+fn width(rows: &[Row]) -> usize {
+    rows.iter().map(|row| row.values.len()).max().unwrap_or(0)
+}
+
+/// Whether the rows are sorted newest first, which is Quandl's default order.
+fn is_descending(rows: &[Row]) -> bool {
+    rows.len() > 1 && rows[0].date > rows[rows.len() - 1].date
+}
+
+/// The rows sorted oldest first.
+fn chronological(rows: &[Row]) -> Vec<&Row> {
+    let mut sorted: Vec<&Row> = rows.iter().collect();
+    sorted.sort_by(|a, b| a.date.cmp(&b.date));
+    sorted
+}
+
+/// First non-missing value of each column.
+fn first_values<'a, I>(rows: I, width: usize) -> Vec<Option<f64>>
+    where I: Iterator<Item = &'a Row>
+{
+    let mut firsts = vec![None; width];
+    for row in rows {
+        for (first, value) in firsts.iter_mut().zip(row.values.iter()) {
+            if first.is_none() {
+                *first = *value;
+            }
+        }
+    }
+    firsts
+}
+
+/// Compute new values for each row in chronological order, returning the rows in the order of
+/// the input. `f` receives the previous row, if any.
+fn map_chronological<F>(rows: &[Row], mut f: F) -> Vec<Row>
+    where F: FnMut(Option<&Row>, &Row) -> Vec<Option<f64>>
+{
+    let mut prev = None;
+    let mut result = Vec::with_capacity(rows.len());
+    for row in chronological(rows) {
+        result.push(Row::new(row.date, f(prev, row)));
+        prev = Some(row);
+    }
+    if is_descending(rows) {
+        result.reverse();
+    }
+    result
+}
+
+/// Map each non-missing value with `f`, which receives the column index.
+fn map_values<F>(rows: &[Row], f: F) -> Vec<Row>
+    where F: Fn(usize, f64) -> Option<f64>
+{
+    rows.iter()
+        .map(|row| {
+            let values = row.values
+                            .iter()
+                            .enumerate()
+                            .map(|(i, value)| value.and_then(|value| f(i, value)))
+                            .collect();
+            Row::new(row.date, values)
+        })
+        .collect()
+}
+
+/// Combine each row with the previous row, dropping the oldest row. Missing values on either
+/// side give a missing value.
+fn pairwise<F>(rows: &[Row], f: F) -> Vec<Row>
+    where F: Fn(f64, f64) -> Option<f64>
+{
+    let mut result = map_chronological(rows, |prev, row| {
+        match prev {
+            Some(prev) => {
+                row.values
+                   .iter()
+                   .enumerate()
+                   .map(|(i, value)| {
+                       match (prev.values.get(i).and_then(|v| *v), *value) {
+                           (Some(prev), Some(cur)) => f(prev, cur),
+                           _ => None,
+                       }
+                   })
+                   .collect()
+            }
+            None => Vec::new(),
+        }
+    });
+    // drop the oldest row, which has no previous row
+    if is_descending(rows) {
+        result.pop();
+    } else if !result.is_empty() {
+        result.remove(0);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use serde_json;
+    use super::*;
+    use super::chronological;
+    use super::super::{Dataset, NaiveDate, Row, Transform, JsonValue};
+
+    fn fixture(json: &str) -> Dataset {
+        let json: JsonValue = serde_json::from_str(json).unwrap();
+        Dataset::from_json(&json).unwrap()
+    }
+
+    fn assert_rows_eq(actual: &[Row], expected: &[Row]) {
+        assert_rows_close(actual, expected, 1e-9);
+    }
+
+    /// Compare the rows, with values equal up to a relative `tolerance`.
+    fn assert_rows_close(actual: &[Row], expected: &[Row], tolerance: f64) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert_eq!(a.date, e.date);
+            assert_eq!(a.values.len(), e.values.len());
+            for (a, e) in a.values.iter().zip(e.values.iter()) {
+                match (*a, *e) {
+                    (Some(a), Some(e)) => assert!((a - e).abs() <= tolerance * e.abs().max(1.0),
+                                                  "{} != {}",
+                                                  a,
+                                                  e),
+                    (a, e) => assert_eq!(a, e),
+                }
+            }
+        }
+    }
+
+    /// Each `transform_expected_*` fixture holds the result of a transform of the rows of
+    /// `transform_input.json`, worked out by hand from the definitions in the Quandl API docs and
+    /// laid out like a Quandl response. They are not captured from the server.
+    #[test]
+    fn test_matches_expected_fixtures() {
+        let raw = fixture(include_str!("../tests/fixtures/transform_input.json"));
+        let cases: Vec<(&str, fn(&[Row]) -> Vec<Row>)> =
+            vec![(include_str!("../tests/fixtures/transform_expected_diff.json"), diff),
+                 (include_str!("../tests/fixtures/transform_expected_rdiff.json"), rdiff),
+                 (include_str!("../tests/fixtures/transform_expected_cumul.json"), cumul),
+                 (include_str!("../tests/fixtures/transform_expected_normalize.json"),
+                  normalize),
+                 (include_str!("../tests/fixtures/transform_expected_rdiff_from.json"),
+                  rdiff_from)];
+        for (json, transform) in cases {
+            let expected = fixture(json);
+            assert_rows_eq(&transform(&raw.rows), &expected.rows);
+
+            // the result doesn't depend on the order of the input
+            let mut asc = raw.rows.clone();
+            asc.reverse();
+            let mut expected_asc = expected.rows.clone();
+            expected_asc.reverse();
+            assert_rows_eq(&transform(&asc), &expected_asc);
+        }
+    }
+
+    /// Compares the local transforms with the transforms applied by Quandl to the same rows of
+    /// `WIKI/AAPL`. Quandl may round the values it returns, hence the looser tolerance.
+    #[cfg(feature = "test-quandl-api")]
+    #[test]
+    fn test_matches_server() {
+        use super::super::Quandl;
+
+        let q = Quandl::new();
+        let request = |transform: Transform| {
+            q.new_request("WIKI", "AAPL")
+             .column_index(4)
+             .start_date(&NaiveDate::from_ymd(2015, 1, 2))
+             .unwrap()
+             .end_date(&NaiveDate::from_ymd(2015, 3, 31))
+             .unwrap()
+             .transform(transform)
+             .run_dataset()
+             .unwrap()
+        };
+        let raw = request(Transform::None);
+        assert!(!raw.rows.is_empty());
+        for &transform in &[Transform::Diff,
+                            Transform::Rdiff,
+                            Transform::RdiffFrom,
+                            Transform::Cumul,
+                            Transform::Normalize] {
+            let server = request(transform);
+            assert_rows_close(&apply(transform, &raw.rows), &server.rows, 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_apply() {
+        let raw = fixture(include_str!("../tests/fixtures/transform_input.json"));
+        assert_eq!(apply(Transform::Diff, &raw.rows), diff(&raw.rows));
+        assert_eq!(apply(Transform::None, &raw.rows), raw.rows);
+        assert_eq!(apply(Transform::Rdiff, &raw.rows), rdiff(&raw.rows));
+        assert_eq!(apply(Transform::RdiffFrom, &raw.rows), rdiff_from(&raw.rows));
+        assert_eq!(apply(Transform::Cumul, &raw.rows), cumul(&raw.rows));
+        assert_eq!(apply(Transform::Normalize, &raw.rows), normalize(&raw.rows));
+    }
+
+    #[test]
+    fn test_properties() {
+        let raw = fixture(include_str!("../tests/fixtures/transform_input.json"));
+        // log returns are the log of one plus the percentage change
+        let logs = log_returns(&raw.rows);
+        let rdiffs = rdiff(&raw.rows);
+        for (log, rdiff) in logs.iter().zip(rdiffs.iter()) {
+            for (log, rdiff) in log.values.iter().zip(rdiff.values.iter()) {
+                assert_eq!(log.is_some(), rdiff.is_some());
+                if let (Some(log), Some(rdiff)) = (*log, *rdiff) {
+                    assert!((log - (1.0 + rdiff).ln()).abs() < 1e-12);
+                }
+            }
+        }
+
+        // the latest row of rdiff_from is always zero
+        let from = rdiff_from(&raw.rows);
+        assert_eq!(from[0].values, vec![Some(0.0), Some(0.0)]);
+
+        // normalize starts at 100
+        let normalized = normalize(&raw.rows);
+        assert_eq!(normalized[normalized.len() - 1].values[0], Some(100.0));
+    }
+
+    /// Deterministic pseudo random numbers for the generated tests, so that failures can be
+    /// reproduced.
+    struct Lcg(u64);
+
+    impl Lcg {
+        /// Uniform in `[0, 1)`.
+        fn uniform(&mut self) -> f64 {
+            self.0 = self.0
+                         .wrapping_mul(6364136223846793005)
+                         .wrapping_add(1442695040888963407);
+            (self.0 >> 11) as f64 / (1u64 << 53) as f64
+        }
+
+        /// `count` consecutive daily rows of `width` values in `[-100, 100)`, oldest or newest
+        /// first at random. Each value is missing with probability `missing`.
+        fn rows(&mut self, count: usize, width: usize, missing: f64) -> Vec<Row> {
+            let mut rows = Vec::with_capacity(count);
+            for i in 0..count {
+                let mut values = Vec::with_capacity(width);
+                for _ in 0..width {
+                    if self.uniform() < missing {
+                        values.push(None);
+                    } else {
+                        values.push(Some(self.uniform() * 200.0 - 100.0));
+                    }
+                }
+                rows.push(Row::new(NaiveDate::from_ymd(2015, 1, 1) + Duration::days(i as i64),
+                                   values));
+            }
+            if self.uniform() < 0.5 {
+                rows.reverse();
+            }
+            rows
+        }
+    }
+
+    /// The row of `row` with its values combined with those of `other`. Missing values on
+    /// either side give a missing value.
+    fn combine<F>(row: &Row, other: &Row, f: F) -> Row
+        where F: Fn(f64, f64) -> f64
+    {
+        let values = row.values
+                        .iter()
+                        .zip(other.values.iter())
+                        .map(|(a, b)| a.and_then(|a| b.map(|b| f(a, b))))
+                        .collect();
+        Row::new(row.date, values)
+    }
+
+    #[test]
+    fn test_generated_properties() {
+        let mut rng = Lcg(42);
+        for _ in 0..200 {
+            let count = 2 + (rng.uniform() * 30.0) as usize;
+            let width = 1 + (rng.uniform() * 3.0) as usize;
+
+            // the cumulative sum of the changes gives back the series, minus its oldest row
+            let rows = rng.rows(count, width, 0.0);
+            let oldest = chronological(&rows)[0].clone();
+            let rebuilt: Vec<Row> = cumul(&diff(&rows))
+                                        .into_iter()
+                                        .map(|row| combine(&row, &oldest, |sum, first| sum + first))
+                                        .collect();
+            let expected: Vec<Row> = rows.iter()
+                                         .filter(|row| row.date != oldest.date)
+                                         .cloned()
+                                         .collect();
+            assert_rows_eq(&rebuilt, &expected);
+
+            // the percentage change is the change divided by the previous value
+            let rows = rng.rows(count, width, 0.2);
+            let sorted = chronological(&rows);
+            let expected: Vec<Row> = diff(&rows)
+                                         .into_iter()
+                                         .map(|row| {
+                                             let i = sorted.iter()
+                                                           .position(|r| r.date == row.date)
+                                                           .unwrap();
+                                             combine(&row, sorted[i - 1], |change, prev| {
+                                                 change / prev
+                                             })
+                                         })
+                                         .collect();
+            assert_rows_eq(&rdiff(&rows), &expected);
+        }
+    }
+
+    #[test]
+    fn test_edge_cases() {
+        assert!(diff(&[]).is_empty());
+        let row = Row::new(NaiveDate::from_ymd(2015, 1, 2), vec![Some(0.0)]);
+        assert!(diff(&[row.clone()]).is_empty());
+        assert_eq!(cumul(&[row.clone()]), vec![row.clone()]);
+
+        let next = Row::new(NaiveDate::from_ymd(2015, 1, 5), vec![Some(1.0)]);
+        // division by zero gives a missing value
+        assert_eq!(rdiff(&[row.clone(), next.clone()]),
+                   vec![Row::new(next.date, vec![None])]);
+        assert_eq!(normalize(&[row.clone(), next.clone()]),
+                   vec![Row::new(row.date, vec![None]), Row::new(next.date, vec![None])]);
+    }
+}
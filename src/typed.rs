@@ -0,0 +1,254 @@
+use error::{Error, Result};
+use super::Row;
+
+/// A type that rows of a dataset can be decoded into by position. The column names of the
+/// response are resolved once with `layout`, after which `decode` reads each row without looking
+/// up names.
+///
+/// With the `derive` feature this trait can be derived with `#[derive(QuandlRow)]`. Fields are
+/// matched to the column with the same name, ignoring case, or to the column given with
+/// `#[quandl(column = "Adj. Close")]`. A field marked `#[quandl(date)]` receives the date of the
+/// row, and a field marked `#[quandl(optional)]` is `None` when its column is missing from the
+/// response. Value fields can be `f64`, `i64` or an `Option` of either for columns with gaps.
+///
+/// ```ignore
+/// #[derive(QuandlRow)]
+/// struct Price {
+///     #[quandl(date)]
+///     date: NaiveDate,
+///     close: f64,
+///     #[quandl(column = "Adj. Close", optional)]
+///     adj_close: Option<f64>,
+/// }
+/// ```
+pub trait QuandlRow: Sized {
+    /// Resolve the columns of the fields among the column names of a response.
+    fn layout(column_names: &[String]) -> Result<RowLayout>;
+
+    /// Decode a row with a layout returned by `layout`.
+    fn decode(layout: &RowLayout, row: &Row) -> Result<Self>;
+}
+
+/// Positions of the columns read by a `QuandlRow` type among the values of a `Row`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowLayout {
+    columns: Vec<(String, Option<usize>)>,
+}
+
+impl RowLayout {
+    /// Find each of `columns` among `column_names`, which start with the date column. Each
+    /// column is given as its name and whether it may be missing. Names are compared exactly
+    /// first and then ignoring case. All required columns that are missing are reported in one
+    /// error.
+    pub fn new(column_names: &[String], columns: &[(&str, bool)]) -> Result<RowLayout> {
+        let value_names = if column_names.is_empty() {
+            &column_names[..]
+        } else {
+            &column_names[1..]
+        };
+        let mut layout = RowLayout { columns: Vec::with_capacity(columns.len()) };
+        let mut missing = Vec::new();
+        for &(name, optional) in columns {
+            let position = value_names.iter()
+                                      .position(|column| column == name)
+                                      .or_else(|| {
+                                          let lower = name.to_lowercase();
+                                          value_names.iter()
+                                                     .position(|column| {
+                                                         column.to_lowercase() == lower
+                                                     })
+                                      });
+            if position.is_none() && !optional {
+                missing.push(format!("`{}`", name));
+            }
+            layout.columns.push((String::from(name), position));
+        }
+        if !missing.is_empty() {
+            return Err(Error::Column(format!("missing columns {}, available columns: {}",
+                                             missing.join(", "),
+                                             value_names.join(", "))));
+        }
+        Ok(layout)
+    }
+
+    /// Like `new`, but each column is given as a list of alternative names in order of
+    /// preference, e.g. `&["Close", "Last"]`, for types that read datasets of several databases.
+    /// A missing column is reported by its first name.
+    pub fn with_alternatives(column_names: &[String],
+                             columns: &[(&[&str], bool)])
+                             -> Result<RowLayout> {
+        let columns: Vec<(&str, bool)> = columns.iter()
+                                                .map(|&(names, optional)| {
+                                                    (find_column(column_names, names), optional)
+                                                })
+                                                .collect();
+        RowLayout::new(column_names, &columns)
+    }
+
+    /// Name of the column of the `field`th column passed to `new`.
+    pub fn column(&self, field: usize) -> &str {
+        &self.columns[field].0
+    }
+
+    /// Value of the `field`th column in `row`, `None` if the value or the column is missing.
+    pub fn value(&self, field: usize, row: &Row) -> Option<f64> {
+        self.columns[field].1.and_then(|position| row.values.get(position).and_then(|v| *v))
+    }
+
+    /// Convert the value of the `field`th column in `row`, failing with the column name and
+    /// the date of the row if it can't be represented as a `T`.
+    pub fn get<T: FromValue>(&self, field: usize, row: &Row) -> Result<T> {
+        let value = self.value(field, row);
+        T::from_value(value).ok_or_else(|| {
+            Error::Column(format!("invalid value `{:?}` in column `{}` of row `{}`",
+                                  value,
+                                  self.column(field),
+                                  row.date))
+        })
+    }
+}
+
+/// The first of `names` that is one of the value columns, ignoring case, or the first of
+/// `names` if none is.
+fn find_column<'a>(column_names: &[String], names: &[&'a str]) -> &'a str {
+    *names.iter()
+          .find(|name| {
+              let lower = name.to_lowercase();
+              column_names.iter().skip(1).any(|column| column.to_lowercase() == lower)
+          })
+          .unwrap_or(&names[0])
+}
+
+/// Types a value of a dataset can be decoded into by `RowLayout::get`.
+pub trait FromValue: Sized {
+    /// Convert a value, `None` if it is missing. Returns `None` if the value can't be
+    /// represented.
+    fn from_value(value: Option<f64>) -> Option<Self>;
+}
+
+impl FromValue for f64 {
+    fn from_value(value: Option<f64>) -> Option<f64> {
+        value
+    }
+}
+
+impl FromValue for i64 {
+    fn from_value(value: Option<f64>) -> Option<i64> {
+        value.and_then(|value| {
+            if value.fract() == 0.0 && value.abs() < 9.007_199_254_740_992e15 {
+                Some(value as i64)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: Option<f64>) -> Option<Option<T>> {
+        match value {
+            Some(_) => T::from_value(value).map(Some),
+            None => Some(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{Dataset, NaiveDate, Row};
+    use error::Result;
+
+    /// What `#[derive(QuandlRow)]` generates.
+    #[derive(Debug, PartialEq)]
+    struct Price {
+        date: NaiveDate,
+        adj_close: f64,
+        volume: Option<i64>,
+        dividend: Option<f64>,
+    }
+
+    impl QuandlRow for Price {
+        fn layout(column_names: &[String]) -> Result<RowLayout> {
+            RowLayout::new(column_names,
+                           &[("Adj. Close", false), ("volume", false), ("dividend", true)])
+        }
+
+        fn decode(layout: &RowLayout, row: &Row) -> Result<Price> {
+            Ok(Price {
+                date: row.date,
+                adj_close: try!(layout.get(0, row)),
+                volume: try!(layout.get(1, row)),
+                dividend: try!(layout.get(2, row)),
+            })
+        }
+    }
+
+    fn dataset() -> Dataset {
+        Dataset {
+            column_names: vec![String::from("Date"),
+                               String::from("Volume"),
+                               String::from("Adj. Close")],
+            rows: vec![Row::new(NaiveDate::from_ymd(2015, 1, 2), vec![Some(100.0), Some(1.5)]),
+                       Row::new(NaiveDate::from_ymd(2015, 1, 1), vec![None, Some(2.5)])],
+        }
+    }
+
+    #[test]
+    fn test_decode_rows() {
+        assert_eq!(dataset().decode_rows::<Price>().unwrap(),
+                   vec![Price {
+                            date: NaiveDate::from_ymd(2015, 1, 2),
+                            adj_close: 1.5,
+                            volume: Some(100),
+                            dividend: None,
+                        },
+                        Price {
+                            date: NaiveDate::from_ymd(2015, 1, 1),
+                            adj_close: 2.5,
+                            volume: None,
+                            dividend: None,
+                        }]);
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let mut dataset = dataset();
+        dataset.column_names[2] = String::from("Close");
+        let err = dataset.decode_rows::<Price>().unwrap_err().to_string();
+        assert!(err.contains("missing columns `Adj. Close`"), err);
+        assert!(err.contains("available columns: Volume, Close"), err);
+
+        let mut dataset = self::dataset();
+        dataset.rows[1].values[1] = None;
+        let err = dataset.decode_rows::<Price>().unwrap_err().to_string();
+        assert!(err.contains("column `Adj. Close` of row `2015-01-01`"), err);
+
+        let mut dataset = self::dataset();
+        dataset.rows[0].values[0] = Some(100.5);
+        assert!(dataset.decode_rows::<Price>().is_err());
+    }
+
+    #[test]
+    fn test_layout() {
+        let names = vec![String::from("Date"), String::from("Open"), String::from("open")];
+        let layout = RowLayout::new(&names, &[("open", false), ("Date", true)]).unwrap();
+        assert_eq!(layout.column(0), "open");
+        let row = Row::new(NaiveDate::from_ymd(2015, 1, 1), vec![Some(1.5), Some(2.5)]);
+        // exact matches win, and the date column is not a value column
+        assert_eq!(layout.value(0, &row), Some(2.5));
+        assert_eq!(layout.value(1, &row), None);
+
+        let names = vec![String::from("Date"), String::from("Last"), String::from("Volume")];
+        let layout = RowLayout::with_alternatives(&names,
+                                                  &[(&["Close", "last"], false),
+                                                    (&["Open Interest"], true)])
+                         .unwrap();
+        assert_eq!(layout.column(0), "last");
+        assert_eq!(layout.value(0, &row), Some(1.5));
+        assert!(RowLayout::with_alternatives(&names, &[(&["Close", "Settle"], false)])
+                    .unwrap_err()
+                    .to_string()
+                    .contains("`Close`"));
+    }
+}
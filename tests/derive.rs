@@ -0,0 +1,59 @@
+#![cfg(feature = "derive")]
+
+extern crate quandl;
+
+use quandl::{Dataset, NaiveDate, QuandlRow, Row};
+
+#[derive(Debug, PartialEq, QuandlRow)]
+struct Price {
+    #[quandl(date)]
+    date: NaiveDate,
+    close: f64,
+    #[quandl(column = "Adj. Close")]
+    adj_close: Option<f64>,
+    volume: i64,
+    #[quandl(column = "Split Ratio", optional)]
+    split_ratio: Option<f64>,
+}
+
+fn dataset() -> Dataset {
+    Dataset {
+        column_names: vec![String::from("Date"),
+                           String::from("Close"),
+                           String::from("Volume"),
+                           String::from("Adj. Close")],
+        rows: vec![Row::new(NaiveDate::from_ymd(2015, 1, 2), vec![Some(1.5), Some(10.0), None]),
+                   Row::new(NaiveDate::from_ymd(2015, 1, 1),
+                            vec![Some(2.5), Some(20.0), Some(0.5)])],
+    }
+}
+
+#[test]
+fn test_derive_decode() {
+    assert_eq!(dataset().decode_rows::<Price>().unwrap(),
+               vec![Price {
+                        date: NaiveDate::from_ymd(2015, 1, 2),
+                        close: 1.5,
+                        adj_close: None,
+                        volume: 10,
+                        split_ratio: None,
+                    },
+                    Price {
+                        date: NaiveDate::from_ymd(2015, 1, 1),
+                        close: 2.5,
+                        adj_close: Some(0.5),
+                        volume: 20,
+                        split_ratio: None,
+                    }]);
+}
+
+#[test]
+fn test_derive_layout() {
+    let layout = Price::layout(&dataset().column_names).unwrap();
+    assert_eq!(layout.column(0), "close");
+    assert_eq!(layout.column(1), "Adj. Close");
+
+    let mut dataset = dataset();
+    dataset.column_names.truncate(3);
+    assert!(Price::layout(&dataset.column_names).is_err());
+}